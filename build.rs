@@ -0,0 +1,11 @@
+fn main() {
+    // leveldb-sys links leveldb and the C++ standard library
+    cc::Build::new()
+        .cpp(true)
+        .std("c++11")
+        .include("shim/include")
+        .file("shim/leveldb_shim.cc")
+        .compile("leveldb_shim");
+
+    println!("cargo:rerun-if-changed=shim");
+}
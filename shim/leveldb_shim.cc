@@ -0,0 +1,85 @@
+// Parts of leveldb that its C API does not expose.
+//
+// The headers in include/ are those of the leveldb release built by
+// leveldb-sys. The structs below mirror their definitions in leveldb's
+// db/c.cc, so the handles created here can be passed to the C API.
+
+#include <stdarg.h>
+#include <stdio.h>
+
+#include <string>
+
+#include "helpers/memenv/memenv.h"
+#include "leveldb/env.h"
+
+struct leveldb_env_t {
+  leveldb::Env* rep;
+  bool is_default;
+};
+
+struct leveldb_logger_t {
+  leveldb::Logger* rep;
+};
+
+namespace {
+
+// Formats the messages logged by leveldb and passes them to a callback.
+class CallbackLogger : public leveldb::Logger {
+ public:
+  CallbackLogger(void* state, void (*destructor)(void*),
+                 void (*log)(void*, const char*, size_t))
+      : state_(state), destructor_(destructor), log_(log) {}
+
+  ~CallbackLogger() override { destructor_(state_); }
+
+  void Logv(const char* format, va_list ap) override {
+    char buffer[512];
+    va_list copy;
+    va_copy(copy, ap);
+    int length = vsnprintf(buffer, sizeof(buffer), format, copy);
+    va_end(copy);
+    if (length < 0) {
+      return;
+    }
+    if (static_cast<size_t>(length) < sizeof(buffer)) {
+      log_(state_, buffer, length);
+      return;
+    }
+
+    // too long for the buffer, format again with room for the terminator
+    std::string message(length + 1, '\0');
+    vsnprintf(&message[0], message.size(), format, ap);
+    log_(state_, message.data(), length);
+  }
+
+ private:
+  void* state_;
+  void (*destructor_)(void*);
+  void (*log_)(void*, const char*, size_t);
+};
+
+}  // namespace
+
+extern "C" {
+
+leveldb_env_t* rs_leveldb_create_mem_env() {
+  leveldb_env_t* result = new leveldb_env_t;
+  result->rep = leveldb::NewMemEnv(leveldb::Env::Default());
+  result->is_default = false;
+  return result;
+}
+
+leveldb_logger_t* rs_leveldb_logger_create(
+    void* state, void (*destructor)(void*),
+    void (*log)(void*, const char*, size_t)) {
+  leveldb_logger_t* result = new leveldb_logger_t;
+  result->rep = new CallbackLogger(state, destructor, log);
+  return result;
+}
+
+void rs_leveldb_logger_destroy(leveldb_logger_t* logger) {
+  delete logger->rep;
+  delete logger;
+}
+
+}  // extern "C"
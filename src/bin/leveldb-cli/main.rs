@@ -0,0 +1,256 @@
+//! leveldb-cli, a tool to inspect and maintain leveldb databases
+
+mod render;
+mod repl;
+mod scan;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use leveldb::compaction::Compaction;
+use leveldb::database::Database;
+use leveldb::iterator::Iterable;
+use leveldb::management::{self, DumpFormat, LoadOptions};
+use leveldb::options::{Options, ReadOptions, WriteOptions};
+use leveldb::properties::{Properties, NUM_LEVELS};
+use render::Encoding;
+use scan::{scan, RangeArgs, ScanRange};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "leveldb-cli", version, about = "Inspect and maintain leveldb databases")]
+struct Cli {
+    /// The path of the database.
+    db: PathBuf,
+    /// The encoding of keys, on the command line and in output.
+    #[arg(long, global = true, value_enum, default_value = "utf8")]
+    key_encoding: Encoding,
+    /// The encoding of values, on the command line and in output.
+    #[arg(long, global = true, value_enum, default_value = "utf8")]
+    value_encoding: Encoding,
+    /// Create the database if it is missing.
+    #[arg(long, global = true)]
+    create: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the value of a key.
+    Get { key: String },
+    /// Set the value of a key.
+    Put { key: String, value: String },
+    /// Delete a key.
+    Delete { key: String },
+    /// Print the entries within a range, one per line.
+    Scan {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Stop after this many entries.
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Print keys only.
+        #[arg(long)]
+        keys_only: bool,
+    },
+    /// Print the number of entries within a range.
+    Count {
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Print leveldb's statistics.
+    Stats,
+    /// Compact the database, or the keys within a range.
+    Compact {
+        /// The first key to compact.
+        #[arg(long)]
+        from: Option<String>,
+        /// The last key to compact.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Repair a damaged database.
+    Repair,
+    /// Delete the database and all of its files.
+    Destroy {
+        /// Confirm the database is to be destroyed.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Write all entries to a dump.
+    Dump {
+        #[arg(long, value_enum, default_value = "binary")]
+        format: Format,
+        /// The file to write, standard output if missing.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Load the entries of a dump into the database.
+    Load {
+        /// The format of the dump, detected if missing.
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// The number of entries written in one batch.
+        #[arg(long, default_value_t = 10_000)]
+        batch_size: usize,
+        /// The file to read, standard input if missing.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Open the database in an interactive shell.
+    Shell {
+        /// The number of entries a scan prints at a time.
+        #[arg(long, default_value_t = 20)]
+        page_size: usize,
+        /// The file to keep the command history in, `~/.leveldb_cli_history` if missing.
+        #[arg(long)]
+        history: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Binary,
+    Jsonl,
+    Csv,
+}
+
+impl From<Format> for DumpFormat {
+    fn from(format: Format) -> DumpFormat {
+        match format {
+            Format::Binary => DumpFormat::Binary,
+            Format::Jsonl => DumpFormat::JsonLines,
+            Format::Csv => DumpFormat::Csv,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn open(cli: &Cli) -> CliResult<Database> {
+    let mut options = Options::new();
+    options.create_if_missing = cli.create;
+    Ok(Database::open(&cli.db, &options)?)
+}
+
+fn run(cli: &Cli) -> CliResult<ExitCode> {
+    let keys = cli.key_encoding;
+    let values = cli.value_encoding;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match &cli.command {
+        Command::Get { key } => {
+            match open(cli)?.get_u8(&ReadOptions::new(), &keys.parse(key)?)? {
+                Some(value) => writeln!(out, "{}", values.render(&value))?,
+                None => {
+                    eprintln!("not found");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        }
+        Command::Put { key, value } => {
+            open(cli)?.put_u8(&WriteOptions::new(), &keys.parse(key)?, &values.parse(value)?)?;
+        }
+        Command::Delete { key } => {
+            open(cli)?.delete_u8(&WriteOptions::new(), &keys.parse(key)?)?;
+        }
+        Command::Scan { range, limit, keys_only } => {
+            let database = Arc::new(open(cli)?);
+            let entries = scan(&database, &ScanRange::parse(range, keys)?);
+            for entry in entries.take(limit.unwrap_or(usize::MAX)) {
+                let (key, value) = entry?;
+                if *keys_only {
+                    writeln!(out, "{}", keys.render(&key))?;
+                } else {
+                    writeln!(out, "{}\t{}", keys.render(&key), values.render(&value))?;
+                }
+            }
+        }
+        Command::Count { range } => {
+            let database = Arc::new(open(cli)?);
+            let mut count = 0u64;
+            for entry in scan(&database, &ScanRange::parse(range, keys)?) {
+                entry?;
+                count += 1;
+            }
+            writeln!(out, "{}", count)?;
+        }
+        Command::Stats => {
+            let database = open(cli)?;
+            for level in 0..NUM_LEVELS {
+                if let Some(files) = database.num_files_at_level(level) {
+                    writeln!(out, "files at level {}: {}", level, files)?;
+                }
+            }
+            if let Some(memory) = database.approximate_memory_usage() {
+                writeln!(out, "approximate memory usage: {}", memory)?;
+            }
+            if let Some(stats) = database.property("leveldb.stats") {
+                write!(out, "{}", stats)?;
+            }
+        }
+        Command::Compact { from, to } => {
+            let database = open(cli)?;
+            let read_opts = ReadOptions::new();
+            // leveldb's bounds are inclusive, default to the first and last key
+            let from = match from {
+                Some(from) => Some(keys.parse(from)?),
+                None => database.keys_iter(&read_opts).next(),
+            };
+            let to = match to {
+                Some(to) => Some(keys.parse(to)?),
+                None => database.keys_iter(&read_opts).last(),
+            };
+            if let (Some(from), Some(to)) = (from, to) {
+                database.compact(&from, &to);
+            }
+        }
+        Command::Repair => management::repair(&cli.db, &Options::new())?,
+        Command::Destroy { yes } => {
+            if !yes {
+                return Err(format!("pass --yes to destroy {}", cli.db.display()).into());
+            }
+            management::destroy(&cli.db, &Options::new())?;
+        }
+        Command::Dump { format, output } => {
+            let database = open(cli)?;
+            let count = match output {
+                Some(path) => management::dump(&database, File::create(path)?, (*format).into())?,
+                None => management::dump(&database, &mut out, (*format).into())?,
+            };
+            eprintln!("dumped {} entries", count);
+        }
+        Command::Load { format, batch_size, input } => {
+            let mut load_opts = LoadOptions::new();
+            load_opts.format = format.map(DumpFormat::from);
+            load_opts.batch_size = *batch_size;
+            load_opts.options.create_if_missing = cli.create;
+            let count = match input {
+                Some(path) => management::load_with(&cli.db, File::open(path)?, &load_opts)?,
+                None => management::load_with(&cli.db, io::stdin().lock(), &load_opts)?,
+            };
+            eprintln!("loaded {} entries", count);
+        }
+        Command::Shell { page_size, history } => {
+            repl::run(Arc::new(open(cli)?), keys, values, *page_size, history.clone())?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
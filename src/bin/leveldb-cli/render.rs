@@ -0,0 +1,89 @@
+//! Parsing and rendering of keys and values
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::ValueEnum;
+
+/// How keys and values are written on the command line and in output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// Text; backslashes are written `\\`, control characters and bytes
+    /// that are not valid UTF-8 are written `\xNN`.
+    Utf8,
+    /// Lowercase hexadecimal.
+    Hex,
+    /// Standard base64.
+    Base64,
+}
+
+impl Encoding {
+    /// Parse `text` given in this encoding.
+    pub fn parse(self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Utf8 => unescape(text),
+            Encoding::Hex => {
+                if !text.len().is_multiple_of(2) || !text.is_ascii() {
+                    return Err(format!("invalid hex {:?}", text));
+                }
+                (0..text.len()).step_by(2)
+                    .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("invalid hex {:?}", text)))
+                    .collect()
+            }
+            Encoding::Base64 => BASE64.decode(text).map_err(|e| format!("invalid base64 {:?}: {}", text, e)),
+        }
+    }
+
+    /// Render `bytes` in this encoding.
+    pub fn render(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => escape(bytes),
+            Encoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::Base64 => BASE64.encode(bytes),
+        }
+    }
+}
+
+// the text of `bytes`, escaped so that `unescape` returns them
+fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let escape_bytes = |text: &mut String, bytes: &[u8]| {
+        for byte in bytes {
+            text.push_str(&format!("\\x{:02x}", byte));
+        }
+    };
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => text.push_str("\\\\"),
+                c if c.is_control() => escape_bytes(&mut text, c.encode_utf8(&mut [0; 4]).as_bytes()),
+                c => text.push(c),
+            }
+        }
+        escape_bytes(&mut text, chunk.invalid());
+    }
+    text
+}
+
+// the bytes of `text`, with `\\` and `\xNN` escapes replaced
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('\\') {
+        bytes.extend_from_slice(&rest.as_bytes()[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix('\\') {
+            bytes.push(b'\\');
+            rest = after;
+            continue;
+        }
+        let byte = rest.strip_prefix('x')
+            .and_then(|hex| hex.get(..2))
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| format!("invalid escape in {:?}, write a backslash as \\\\", text))?;
+        bytes.push(byte);
+        rest = &rest[3..];
+    }
+    bytes.extend_from_slice(rest.as_bytes());
+    Ok(bytes)
+}
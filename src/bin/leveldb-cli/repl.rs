@@ -0,0 +1,403 @@
+//! The interactive shell
+//!
+//! The shell keeps the database open between commands. Scans print a page
+//! of entries at a time, `next` prints the following page. `snapshot` pins
+//! the current state of the database: reads see it until `release`, while
+//! writes still go to the database. Keys are completed from the pinned
+//! snapshot as well.
+
+use crate::render::Encoding;
+use crate::scan::{scan, Entry, RangeArgs, ScanRange};
+use crate::CliResult;
+use clap::{Parser, Subcommand};
+use leveldb::database::Database;
+use leveldb::iterator::OwnedIterable;
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::snapshots::ArcSnapshot;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::env;
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// the most keys offered when completing a key prefix
+const MAX_COMPLETIONS: usize = 100;
+
+const HISTORY_FILE: &str = ".leveldb_cli_history";
+
+/// A line entered in the shell
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    command: ShellCommand,
+}
+
+#[derive(Subcommand)]
+enum ShellCommand {
+    /// Print the value of a key.
+    Get { key: String },
+    /// Set the value of a key.
+    Put { key: String, value: String },
+    /// Delete a key.
+    Delete { key: String },
+    /// Print the first page of the entries within a range.
+    Scan {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Print keys only.
+        #[arg(long)]
+        keys_only: bool,
+    },
+    /// Print the next page of the last scan.
+    Next,
+    /// Print the number of entries within a range.
+    Count {
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Pin a snapshot, reads see the database as it is now until it is released.
+    Snapshot,
+    /// Release the pinned snapshot.
+    Release,
+    /// Leave the shell.
+    #[command(alias = "quit")]
+    Exit,
+}
+
+const COMMANDS: &[&str] = &["get", "put", "delete", "scan", "next", "count", "snapshot", "release", "help", "exit", "quit"];
+
+// the rest of a scan
+struct Page {
+    entries: Peekable<Box<dyn Iterator<Item = Entry>>>,
+    keys_only: bool,
+}
+
+// the snapshot pinned with `snapshot`, shared with the completer
+type Pinned = Arc<Mutex<Option<ArcSnapshot>>>;
+
+fn lock(pinned: &Pinned) -> MutexGuard<'_, Option<ArcSnapshot>> {
+    pinned.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct Shell {
+    database: Arc<Database>,
+    snapshot: Pinned,
+    page: Option<Page>,
+    page_size: usize,
+    keys: Encoding,
+    values: Encoding,
+}
+
+/// Run the shell on `database` until the input ends or `exit` is entered.
+pub fn run(database: Arc<Database>,
+           keys: Encoding,
+           values: Encoding,
+           page_size: usize,
+           history: Option<PathBuf>)
+           -> CliResult<()> {
+    let history = history.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE)));
+    let mut editor: Editor<KeyCompleter, DefaultHistory> = Editor::new()?;
+    let snapshot = Pinned::default();
+    editor.set_helper(Some(KeyCompleter { database: database.clone(), snapshot: snapshot.clone(), keys }));
+    if let Some(ref history) = history {
+        // a missing history file is created on exit
+        let _ = editor.load_history(history);
+    }
+
+    let mut shell = Shell { database, snapshot, page: None, page_size: page_size.max(1), keys, values };
+    loop {
+        let prompt = if lock(&shell.snapshot).is_some() { "leveldb (snapshot)> " } else { "leveldb> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line.as_str())?;
+
+        let words = match split_words(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                continue;
+            }
+        };
+        let command = match Line::try_parse_from(words) {
+            Ok(line) => line.command,
+            Err(e) => {
+                // also prints the help requested with `help`
+                let _ = e.print();
+                continue;
+            }
+        };
+        if let ShellCommand::Exit = command {
+            break;
+        }
+        if let Err(e) = shell.execute(command) {
+            eprintln!("error: {}", e);
+        }
+    }
+
+    if let Some(ref history) = history {
+        if let Err(e) = editor.save_history(history) {
+            eprintln!("cannot save history to {}: {}", history.display(), e);
+        }
+    }
+    Ok(())
+}
+
+impl Shell {
+    fn execute(&mut self, command: ShellCommand) -> CliResult<()> {
+        let (keys, values) = (self.keys, self.values);
+        match command {
+            ShellCommand::Get { key } => {
+                let key = keys.parse(&key)?;
+                let value = match *lock(&self.snapshot) {
+                    Some(ref snapshot) => snapshot.get_u8(&ReadOptions::new(), &key)?,
+                    None => self.database.get_u8(&ReadOptions::new(), &key)?,
+                };
+                match value {
+                    Some(value) => println!("{}", values.render(&value)),
+                    None => println!("(not found)"),
+                }
+            }
+            ShellCommand::Put { key, value } => {
+                self.database.put_u8(&WriteOptions::new(), &keys.parse(&key)?, &values.parse(&value)?)?;
+            }
+            ShellCommand::Delete { key } => {
+                self.database.delete_u8(&WriteOptions::new(), &keys.parse(&key)?)?;
+            }
+            ShellCommand::Scan { range, keys_only } => {
+                let entries = self.scan(&ScanRange::parse(&range, keys)?);
+                self.page = Some(Page { entries: entries.peekable(), keys_only });
+                self.print_page()?;
+            }
+            ShellCommand::Next => {
+                if self.page.is_none() {
+                    return Err("no scan to continue".into());
+                }
+                self.print_page()?;
+            }
+            ShellCommand::Count { range } => {
+                let mut count = 0u64;
+                for entry in self.scan(&ScanRange::parse(&range, keys)?) {
+                    entry?;
+                    count += 1;
+                }
+                println!("{}", count);
+            }
+            ShellCommand::Snapshot => {
+                *lock(&self.snapshot) = Some(ArcSnapshot::new(self.database.clone()));
+                println!("snapshot pinned");
+            }
+            ShellCommand::Release => {
+                if lock(&self.snapshot).take().is_none() {
+                    return Err("no snapshot is pinned".into());
+                }
+                println!("snapshot released");
+            }
+            ShellCommand::Exit => {}
+        }
+
+        Ok(())
+    }
+
+    fn scan(&self, range: &ScanRange) -> Box<dyn Iterator<Item = Entry>> {
+        match *lock(&self.snapshot) {
+            Some(ref snapshot) => Box::new(scan(snapshot, range)),
+            None => Box::new(scan(&self.database, range)),
+        }
+    }
+
+    fn print_page(&mut self) -> CliResult<()> {
+        let page = match self.page {
+            Some(ref mut page) => page,
+            None => return Ok(()),
+        };
+        for entry in page.entries.by_ref().take(self.page_size) {
+            let (key, value) = entry?;
+            if page.keys_only {
+                println!("{}", self.keys.render(&key));
+            } else {
+                println!("{}\t{}", self.keys.render(&key), self.values.render(&value));
+            }
+        }
+
+        if page.entries.peek().is_some() {
+            println!("-- more, enter `next` to continue --");
+        } else {
+            self.page = None;
+        }
+        Ok(())
+    }
+}
+
+/// Split `line` into words at whitespace. Double quotes group words,
+/// a backslash escapes the next character within them.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(words);
+        }
+
+        let mut word = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '\\' if quoted => word.push(chars.next().ok_or("unfinished escape")?),
+                c if c.is_whitespace() && !quoted => break,
+                c => word.push(c),
+            }
+        }
+        if quoted {
+            return Err("unfinished quote".to_string());
+        }
+        words.push(word);
+    }
+}
+
+// quote `word` if `split_words` would not return it as it is
+fn quote(word: &str) -> String {
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+/// Completes command names, and keys by their prefix
+struct KeyCompleter {
+    database: Arc<Database>,
+    snapshot: Pinned,
+    keys: Encoding,
+}
+
+impl KeyCompleter {
+    // the first keys starting with `prefix`, in the pinned snapshot if there is one
+    fn keys_with_prefix(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        let entries = match *lock(&self.snapshot) {
+            Some(ref snapshot) => snapshot.owned_prefix_iter(&ReadOptions::new(), prefix),
+            None => self.database.owned_prefix_iter(&ReadOptions::new(), prefix),
+        };
+        entries.take(MAX_COMPLETIONS).map(|(key, _)| key).collect()
+    }
+}
+
+impl Completer for KeyCompleter {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+
+        if start == 0 {
+            let commands = COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()).collect();
+            return Ok((start, commands));
+        }
+        if word.starts_with('-') || word.starts_with('"') {
+            return Ok((start, Vec::new()));
+        }
+        // partial words may not parse, e.g. an odd number of hex digits
+        let prefix = match self.keys.parse(word) {
+            Ok(prefix) => prefix,
+            Err(_) => return Ok((start, Vec::new())),
+        };
+        let candidates = self.keys_with_prefix(&prefix).iter()
+            .map(|key| quote(&self.keys.render(key)))
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for KeyCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for KeyCompleter {}
+
+impl Validator for KeyCompleter {}
+
+impl Helper for KeyCompleter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use leveldb::options::Options;
+
+    fn completer(keys: &[&[u8]]) -> KeyCompleter {
+        let database = Arc::new(Database::open_in_memory(&Options::new()).unwrap());
+        for key in keys {
+            database.put_u8(&WriteOptions::new(), key, b"v").unwrap();
+        }
+        KeyCompleter { database, snapshot: Pinned::default(), keys: Encoding::Utf8 }
+    }
+
+    fn complete(completer: &KeyCompleter, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        completer.complete(line, line.len(), &Context::new(&history)).unwrap()
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("  get  a ").unwrap(), vec!["get", "a"]);
+        assert_eq!(split_words(r#"put "a b" "x\"y\\z""#).unwrap(), vec!["put", "a b", r#"x"y\z"#]);
+        assert_eq!(split_words(r#"get a"b c"d"#).unwrap(), vec!["get", "ab cd"]);
+        assert_eq!(split_words(r#"get \xff"#).unwrap(), vec!["get", r#"\xff"#]);
+        assert_eq!(split_words(r#"put "" x"#).unwrap(), vec!["put", "", "x"]);
+        assert!(split_words(r#"get "a"#).is_err());
+        assert!(split_words(r#"get "a\"#).is_err());
+    }
+
+    #[test]
+    fn test_quote_round_trips() {
+        for word in ["plain", "", "a b", r#"x"y"#, r#"\xff\\"#, "tab\there"] {
+            assert_eq!(split_words(&quote(word)).unwrap(), vec![word]);
+        }
+        assert_eq!(quote("plain"), "plain");
+    }
+
+    #[test]
+    fn test_complete_commands_and_keys() {
+        let completer = completer(&[b"user/1", b"user/2", b"order/1", b"user/\xff", b"user/a b"]);
+
+        assert_eq!(complete(&completer, "sn"), (0, vec!["snapshot".to_string()]));
+        let (start, keys) = complete(&completer, "get user/");
+        assert_eq!((start, keys.len()), (4, 4));
+        // the completed words parse back to the keys
+        for key in &keys {
+            let word = &split_words(key).unwrap()[0];
+            assert!(Encoding::Utf8.parse(word).unwrap().starts_with(b"user/"));
+        }
+        assert!(keys.contains(&r#""user/\\xff""#.to_string()));
+        assert!(keys.contains(&r#""user/a b""#.to_string()));
+        assert_eq!(complete(&completer, "get --fr").1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_complete_from_pinned_snapshot() {
+        let completer = completer(&[b"user/1"]);
+        *lock(&completer.snapshot) = Some(ArcSnapshot::new(completer.database.clone()));
+        completer.database.put_u8(&WriteOptions::new(), b"user/2", b"v").unwrap();
+        assert_eq!(complete(&completer, "get user/").1, vec!["user/1".to_string()]);
+
+        lock(&completer.snapshot).take();
+        assert_eq!(complete(&completer, "get user/").1, vec!["user/1".to_string(), "user/2".to_string()]);
+    }
+}
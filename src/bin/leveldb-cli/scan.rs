@@ -0,0 +1,62 @@
+//! Key ranges and scans, shared by the commands and the shell
+
+use crate::render::Encoding;
+use clap::Args;
+use leveldb::error::Error;
+use leveldb::iterator::{LevelDBIterator, OwnedIterable};
+use leveldb::options::ReadOptions;
+use std::ops::Bound;
+
+/// A key range, `--from` inclusive and `--to` exclusive
+#[derive(Args)]
+pub struct RangeArgs {
+    /// Only keys starting with this prefix.
+    #[arg(long)]
+    prefix: Option<String>,
+    /// Only keys at or after this key.
+    #[arg(long)]
+    from: Option<String>,
+    /// Only keys before this key.
+    #[arg(long)]
+    to: Option<String>,
+}
+
+/// A parsed key range
+pub struct ScanRange {
+    prefix: Vec<u8>,
+    from: Option<Vec<u8>>,
+    to: Option<Vec<u8>>,
+}
+
+impl ScanRange {
+    /// Parse the keys of `range` given in the encoding `keys`.
+    pub fn parse(range: &RangeArgs, keys: Encoding) -> Result<ScanRange, String> {
+        let parse = |key: &Option<String>| key.as_deref().map(|key| keys.parse(key)).transpose();
+
+        Ok(ScanRange {
+            prefix: parse(&range.prefix)?.unwrap_or_default(),
+            from: parse(&range.from)?,
+            to: parse(&range.to)?,
+        })
+    }
+}
+
+pub type Entry = Result<(Vec<u8>, Vec<u8>), Error>;
+
+/// The entries of `source` within `range`, in key order.
+pub fn scan<S: OwnedIterable>(source: &S, range: &ScanRange) -> impl Iterator<Item = Entry> {
+    // keys with the prefix start at the prefix itself
+    let start = match &range.from {
+        Some(from) if *from > range.prefix => from.clone(),
+        _ => range.prefix.clone(),
+    };
+    let end = match &range.to {
+        Some(to) => Bound::Excluded(to.clone()),
+        None => Bound::Unbounded,
+    };
+    let prefix = range.prefix.clone();
+
+    source.owned_range(&ReadOptions::new(), (Bound::Included(start), end))
+        .fallible()
+        .take_while(move |entry| entry.as_ref().map_or(true, |(key, _)| key.starts_with(&prefix)))
+}
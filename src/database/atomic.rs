@@ -0,0 +1,165 @@
+//! Atomic read-modify-write operations
+//!
+//! leveldb has no conditional writes. The operations in this module read a
+//! key and write it while holding a lock for the key, so they are atomic
+//! with respect to each other when used through the same `Database` handle.
+//! Plain `put`, `delete` and batch writes do not take these locks.
+//! `update` calls its closure without holding a lock and writes the result
+//! with `compare_and_swap`, retrying if the key changed in between.
+//!
+//! Locks are striped: keys are hashed onto a fixed number of locks, so
+//! unrelated keys may occasionally wait for each other.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::{IntoLevelDBKey, key_to_vec};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+const STRIPES: usize = 64;
+
+/// A fixed set of locks, each guarding the keys hashed onto it.
+#[derive(Debug)]
+pub(crate) struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl KeyLocks {
+    pub(crate) fn new() -> KeyLocks {
+        KeyLocks { stripes: (0..STRIPES).map(|_| Mutex::new(())).collect() }
+    }
+
+    fn stripe(&self, key: &[u8]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.stripes.len() as u64) as usize
+    }
+
+    /// Lock `key` until the guard is dropped.
+    pub(crate) fn lock(&self, key: &[u8]) -> MutexGuard<'_, ()> {
+        // a panic in another holder cannot leave `()` inconsistent
+        self.stripes[self.stripe(key)].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock all of `keys` until the guards are dropped.
+    ///
+    /// Stripes are locked in ascending order, so concurrent callers
+    /// cannot deadlock.
+    pub(crate) fn lock_many<K: AsRef<[u8]>>(&self, keys: &[K]) -> Vec<MutexGuard<'_, ()>> {
+        let mut stripes: Vec<usize> = keys.iter().map(|key| self.stripe(key.as_ref())).collect();
+        stripes.sort_unstable();
+        stripes.dedup();
+
+        stripes.into_iter()
+            .map(|stripe| self.stripes[stripe].lock().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+}
+
+impl Database {
+    /// Set `key` to `new` if its current value is `expected`.
+    ///
+    /// `None` stands for a missing key: an `expected` of `None` only swaps
+    /// if the key does not exist, a `new` of `None` deletes the key.
+    /// Returns whether the value was swapped.
+    pub fn compare_and_swap(&self,
+                            options: &WriteOptions,
+                            key: &dyn IntoLevelDBKey,
+                            expected: Option<&[u8]>,
+                            new: Option<&[u8]>)
+                            -> Result<bool, Error> {
+        self.compare_and_swap_u8(options, &key_to_vec(key), expected, new)
+    }
+
+    pub fn compare_and_swap_u8(&self,
+                               options: &WriteOptions,
+                               key: &[u8],
+                               expected: Option<&[u8]>,
+                               new: Option<&[u8]>)
+                               -> Result<bool, Error> {
+        let _guard = self.key_locks.lock(key);
+
+        let current = self.get_u8(&ReadOptions::new(), key)?;
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+        self.write_value(options, key, new)?;
+
+        Ok(true)
+    }
+
+    /// Replace the value of `key` with the result of `f`.
+    ///
+    /// `f` receives the current value, or `None` if the key is missing, and
+    /// returns the new value, or `None` to delete the key. Returns the new value.
+    ///
+    /// `f` runs without holding a lock, so it may use the database. It is
+    /// called again whenever the key changed before the new value was written.
+    pub fn update<F>(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, f: F) -> Result<Option<Vec<u8>>, Error>
+        where F: FnMut(Option<&[u8]>) -> Option<Vec<u8>>
+    {
+        self.update_u8(options, &key_to_vec(key), f)
+    }
+
+    pub fn update_u8<F>(&self, options: &WriteOptions, key: &[u8], mut f: F) -> Result<Option<Vec<u8>>, Error>
+        where F: FnMut(Option<&[u8]>) -> Option<Vec<u8>>
+    {
+        loop {
+            let current = self.get_u8(&ReadOptions::new(), key)?;
+            let new = f(current.as_deref());
+            if self.compare_and_swap_u8(options, key, current.as_deref(), new.as_deref())? {
+                return Ok(new);
+            }
+        }
+    }
+
+    /// Add `delta` to the counter stored at `key` and return the new value.
+    ///
+    /// Counters are stored as 8 big-endian bytes, a missing key counts as 0.
+    /// Fails if the stored value is not a counter or the addition overflows.
+    pub fn increment_u64(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, delta: u64) -> Result<u64, Error> {
+        self.increment_with(options, &key_to_vec(key), |value| {
+            u64::from_be_bytes(value).checked_add(delta).map(u64::to_be_bytes)
+        }).map(u64::from_be_bytes)
+    }
+
+    /// Add `delta` to the signed counter stored at `key` and return the new value.
+    ///
+    /// Counters are stored as 8 big-endian bytes, a missing key counts as 0.
+    /// Fails if the stored value is not a counter or the addition overflows.
+    pub fn increment_i64(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, delta: i64) -> Result<i64, Error> {
+        self.increment_with(options, &key_to_vec(key), |value| {
+            i64::from_be_bytes(value).checked_add(delta).map(i64::to_be_bytes)
+        }).map(i64::from_be_bytes)
+    }
+
+    fn increment_with<F>(&self, options: &WriteOptions, key: &[u8], add: F) -> Result<[u8; 8], Error>
+        where F: FnOnce([u8; 8]) -> Option<[u8; 8]>
+    {
+        let _guard = self.key_locks.lock(key);
+
+        let current = match self.get_u8(&ReadOptions::new(), key)? {
+            Some(value) => {
+                let mut bytes = [0u8; 8];
+                if value.len() != bytes.len() {
+                    return Err(Error::new(format!("value of length {} is not a counter", value.len())));
+                }
+                bytes.copy_from_slice(&value);
+                bytes
+            }
+            None => [0u8; 8],
+        };
+        let new = add(current).ok_or_else(|| Error::new("counter overflow".to_string()))?;
+        self.put_u8(options, key, &new)?;
+
+        Ok(new)
+    }
+
+    fn write_value(&self, options: &WriteOptions, key: &[u8], value: Option<&[u8]>) -> Result<(), Error> {
+        match value {
+            Some(value) => self.put_u8(options, key, value),
+            None => self.delete_u8(options, key),
+        }
+    }
+}
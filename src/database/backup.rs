@@ -0,0 +1,251 @@
+//! Online backups
+//!
+//! `Database::backup_to` copies a snapshot of a live database into a second
+//! database at another path, in large `WriteBatch` chunks. Once written, the
+//! backup is read back and its entry count and checksum are compared with
+//! those of the snapshot. A verified backup gets a manifest file recording
+//! both.
+//!
+//! Backing up to the path of a previous backup is incremental: the snapshot
+//! is diffed against the backup, and only changed entries are written.
+//! A path holding a database without a manifest, such as an interrupted
+//! backup, is refused.
+//!
+//! Databases with a custom comparator cannot be backed up, since the backup
+//! is opened with the default one.
+use super::Database;
+use super::error::Error;
+use super::options::{Options, ReadOptions, WriteOptions};
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, LevelDBIterator};
+use super::snapshots::Snapshots;
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// The name of the manifest file in a backup directory.
+pub const MANIFEST_FILE: &str = "BACKUP-MANIFEST";
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Options for `Database::backup_to_with`
+#[derive(Clone, Copy, Debug)]
+pub struct BackupOptions {
+    /// The maximum number of writes in one `WriteBatch`.
+    pub batch_size: usize,
+    /// `fsync` every batch written to the backup, rather than only the last.
+    ///
+    /// The last batch and the manifest are synced either way, so a backup
+    /// with a manifest survives a crash of the machine.
+    pub sync: bool,
+}
+
+impl BackupOptions {
+    pub fn new() -> BackupOptions {
+        BackupOptions {
+            batch_size: 10_000,
+            sync: true,
+        }
+    }
+}
+
+impl Default for BackupOptions {
+    fn default() -> BackupOptions {
+        BackupOptions::new()
+    }
+}
+
+/// The outcome of a backup
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackupInfo {
+    /// The number of entries in the backup.
+    pub entries: u64,
+    /// The CRC32 of all keys and values in the backup, in key order.
+    pub checksum: u32,
+    /// The number of entries written, all of them unless the backup was incremental.
+    pub written: u64,
+    /// The number of entries deleted from a previous backup.
+    pub deleted: u64,
+}
+
+// counts entries and checksums them in key order
+struct Digest {
+    entries: u64,
+    hasher: crc32fast::Hasher,
+}
+
+impl Digest {
+    fn new() -> Digest {
+        Digest { entries: 0, hasher: crc32fast::Hasher::new() }
+    }
+
+    fn add(&mut self, key: &[u8], value: &[u8]) {
+        // lengths keep ("ab", "c") apart from ("a", "bc")
+        self.hasher.update(&(key.len() as u64).to_be_bytes());
+        self.hasher.update(key);
+        self.hasher.update(&(value.len() as u64).to_be_bytes());
+        self.hasher.update(value);
+        self.entries += 1;
+    }
+
+    fn finish(self) -> (u64, u32) {
+        (self.entries, self.hasher.finalize())
+    }
+}
+
+fn io_error(what: &str, path: &Path, e: std::io::Error) -> Error {
+    Error::new(format!("{} {}: {}", what, path.display(), e))
+}
+
+fn write_manifest(path: &Path, entries: u64, checksum: u32) -> Result<(), Error> {
+    let manifest = format!("version {}\nentries {}\nchecksum {:08x}\n", MANIFEST_VERSION, entries, checksum);
+    // write a complete file or none
+    let tmp = path.join(format!("{}.tmp", MANIFEST_FILE));
+    File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(manifest.as_bytes())?;
+            file.sync_all()
+        })
+        .map_err(|e| io_error("cannot write", &tmp, e))?;
+    fs::rename(&tmp, path.join(MANIFEST_FILE)).map_err(|e| io_error("cannot write manifest in", path, e))?;
+    sync_dir(path)
+}
+
+// makes the rename durable, directories cannot be opened for syncing on windows
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<(), Error> {
+    File::open(path).and_then(|dir| dir.sync_all()).map_err(|e| io_error("cannot sync", path, e))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Read the entry count and checksum recorded in the manifest of the backup at `path`.
+pub fn read_manifest(path: &Path) -> Result<(u64, u32), Error> {
+    let file = path.join(MANIFEST_FILE);
+    let manifest = fs::read_to_string(&file).map_err(|e| io_error("cannot read", &file, e))?;
+    let invalid = || Error::new(format!("invalid backup manifest {}", file.display()));
+
+    let mut fields = manifest.lines().map(|line| line.split_once(' ').ok_or_else(invalid));
+    let mut field = |name: &str| -> Result<String, Error> {
+        match fields.next() {
+            Some(Ok((key, value))) if key == name => Ok(value.to_string()),
+            _ => Err(invalid()),
+        }
+    };
+    if field("version")? != MANIFEST_VERSION.to_string() {
+        return Err(Error::new(format!("unsupported backup manifest version in {}", file.display())));
+    }
+    let entries = field("entries")?.parse().map_err(|_| invalid())?;
+    let checksum = u32::from_str_radix(&field("checksum")?, 16).map_err(|_| invalid())?;
+
+    Ok((entries, checksum))
+}
+
+impl Database {
+    /// Back up the current state of the database to `path`.
+    ///
+    /// See `backup_to_with`.
+    pub fn backup_to(&self, path: &Path) -> Result<BackupInfo, Error> {
+        self.backup_to_with(path, &BackupOptions::new())
+    }
+
+    /// Back up the current state of the database to `path`.
+    ///
+    /// Creates a new backup if `path` does not exist or is an empty
+    /// directory, and updates the backup at `path` otherwise. Fails if the
+    /// written backup does not match the snapshot it was taken from.
+    pub fn backup_to_with(&self, path: &Path, backup_options: &BackupOptions) -> Result<BackupInfo, Error> {
+        if self.compare_fn().is_some() {
+            return Err(Error::new("cannot back up a database with a custom comparator".to_string()));
+        }
+        let incremental = path.join(MANIFEST_FILE).exists();
+        let is_empty = |path: &Path| fs::read_dir(path).map_or(true, |mut entries| entries.next().is_none());
+        if !incremental && !is_empty(path) {
+            return Err(Error::new(format!("{} is not empty and holds no backup", path.display())));
+        }
+
+        let mut options = Options::new();
+        options.create_if_missing = !incremental;
+        let backup = Database::open(path, &options)?;
+        if incremental {
+            // the backup is incomplete until it is verified again
+            let manifest = path.join(MANIFEST_FILE);
+            fs::remove_file(&manifest).map_err(|e| io_error("cannot remove", &manifest, e))?;
+        }
+
+        let read_opts = ReadOptions::new();
+        let mut write_opts = WriteOptions::new();
+        write_opts.sync = backup_options.sync;
+        let batch_size = backup_options.batch_size.max(1);
+
+        let snapshot = self.snapshot();
+        let mut source = snapshot.iter(&read_opts).fallible();
+        let mut target = backup.iter(&read_opts).fallible();
+        let mut digest = Digest::new();
+        let mut info = BackupInfo { entries: 0, checksum: 0, written: 0, deleted: 0 };
+
+        let batch = WriteBatch::new();
+        let mut batched = 0;
+        let mut next_source = source.next().transpose()?;
+        let mut next_target = target.next().transpose()?;
+        // both iterate in key order, so a merge join finds the differences
+        loop {
+            let order = match (&next_source, &next_target) {
+                (Some((source_key, _)), Some((target_key, _))) => source_key.cmp(target_key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less | Ordering::Equal => {
+                    let (key, value) = next_source.take().unwrap();
+                    let unchanged = order == Ordering::Equal
+                        && next_target.take().is_some_and(|(_, target_value)| target_value == value);
+                    if !unchanged {
+                        batch.put_u8(&key, &value);
+                        batched += 1;
+                        info.written += 1;
+                    }
+                    digest.add(&key, &value);
+                    next_source = source.next().transpose()?;
+                    if order == Ordering::Equal {
+                        next_target = target.next().transpose()?;
+                    }
+                }
+                Ordering::Greater => {
+                    let (key, _) = next_target.take().unwrap();
+                    batch.delete_u8(&key);
+                    batched += 1;
+                    info.deleted += 1;
+                    next_target = target.next().transpose()?;
+                }
+            }
+
+            if batched >= batch_size {
+                backup.write(&write_opts, &batch)?;
+                batch.clear();
+                batched = 0;
+            }
+        }
+        // also syncs the batches written before, even if empty
+        write_opts.sync = true;
+        backup.write(&write_opts, &batch)?;
+        (info.entries, info.checksum) = digest.finish();
+
+        let mut written = Digest::new();
+        for entry in backup.iter(&read_opts).fallible() {
+            let (key, value) = entry?;
+            written.add(&key, &value);
+        }
+        if written.finish() != (info.entries, info.checksum) {
+            return Err(Error::new(format!("backup at {} does not match the database", path.display())));
+        }
+        write_manifest(path, info.entries, info.checksum)?;
+
+        Ok(info)
+    }
+}
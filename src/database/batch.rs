@@ -5,6 +5,12 @@ use super::options::{WriteOptions, c_writeoptions};
 use super::error::Error;
 use super::db::Database;
 use super::key::IntoLevelDBKey;
+use super::changes::ChangeEvent;
+
+extern "C" {
+    // not exposed by leveldb-sys, but part of the leveldb C API
+    fn leveldb_writebatch_append(destination: *mut leveldb_writebatch_t, source: *const leveldb_writebatch_t);
+}
 
 pub(crate) struct RawWriteBatch {
     pub(crate) ptr: *mut leveldb_writebatch_t,
@@ -30,22 +36,43 @@ pub trait Batch {
 
 impl Batch for Database {
     fn write(&self, options: &WriteOptions, batch: &WriteBatch) -> Result<(), Error> {
-        unsafe {
-            let mut error = ptr::null_mut();
-            let c_write_options = c_writeoptions(options);
-
-            leveldb_write(
-                self.database.ptr,
-                c_write_options,
-                batch.write_batch.ptr,
-                &mut error
-            );
-
-            if error == ptr::null_mut() {
-                Ok(())
-            } else {
-                Err(Error::new_from_char(error))
-            }
+        if let Some(ref merge) = self.merge {
+            return self.publish(|| {
+                let _writes = merge.lock_writes();
+                // written keys lose their pending operands in the same write
+                let discarding = self.discarding_operands(batch);
+                write_raw(self, options, &discarding)
+            }, || ChangeEvent::from_batch(batch));
+        }
+        self.write_keeping_operands(options, batch)
+    }
+}
+
+impl Database {
+    /// Write `batch` as it is, even if a merge operator is set.
+    pub(crate) fn write_keeping_operands(&self, options: &WriteOptions, batch: &WriteBatch) -> Result<(), Error> {
+        self.publish(|| write_raw(self, options, batch), || ChangeEvent::from_batch(batch))
+    }
+}
+
+pub(crate) fn write_raw(database: &Database, options: &WriteOptions, batch: &WriteBatch) -> Result<(), Error> {
+    unsafe {
+        let mut error = ptr::null_mut();
+        let c_write_options = c_writeoptions(options);
+
+        leveldb_write(
+            database.database.ptr,
+            c_write_options,
+            batch.write_batch.ptr,
+            &mut error
+        );
+
+        leveldb_writeoptions_destroy(c_write_options);
+
+        if error.is_null() {
+            Ok(())
+        } else {
+            Err(Error::new_from_char(error))
         }
     }
 }
@@ -102,15 +129,24 @@ impl WriteBatch {
         }
     }
 
+    /// Add the operations of `other` after those of this batch.
+    pub(crate) fn append(&self, other: &WriteBatch) {
+        unsafe { leveldb_writebatch_append(self.write_batch.ptr, other.write_batch.ptr) };
+    }
+
     /// Iterate over the writeBatch, returning the resulting iterator
-    pub fn iterate<T: WriteBatchIterator>(&mut self, iterator: Box<T>) -> Box<T> {
+    pub fn iterate<T: WriteBatchIterator>(&mut self, mut iterator: Box<T>) -> Box<T> {
+        self.for_each(&mut *iterator);
+        iterator
+    }
+
+    /// Pass the operations of the batch to `iterator`, in the order they were added.
+    pub(crate) fn for_each<T: WriteBatchIterator>(&self, iterator: &mut T) {
         unsafe {
-            let iter = Box::into_raw(iterator);
             leveldb_writebatch_iterate(self.write_batch.ptr,
-                                       iter as *mut c_void,
+                                       iterator as *mut T as *mut c_void,
                                        put_callback::<T>,
                                        deleted_callback::<T>);
-            Box::from_raw(iter)
         }
     }
 }
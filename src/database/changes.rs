@@ -0,0 +1,215 @@
+//! Change subscriptions
+//!
+//! `Database::subscribe` returns a channel receiving an event for every
+//! `put`, `delete` and `Batch::write` made through the same `Database`
+//! handle. Writes made through other handles or processes are not seen.
+//!
+//! Events describe the records as they are stored: writes of keyspaces,
+//! TTL values and merge operands show up with their internal keys and
+//! values. The events of a write are sent once it succeeded, in the order
+//! of the operations, and writes are reported in the order they were
+//! applied.
+//!
+//! While a handle has subscribers, its writes are applied one at a time to
+//! number them. Events are queued in that order and sent by the writing
+//! threads once no lock is held, so filters may use the database.
+use super::Database;
+use super::error::Error;
+use super::batch::{WriteBatch, WriteBatchIterator};
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// The kind of a change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Put,
+    Delete,
+}
+
+/// A write made to the database
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub key: Vec<u8>,
+    /// The value written, `None` for deletes.
+    pub value: Option<Vec<u8>>,
+}
+
+impl ChangeEvent {
+    pub(crate) fn put(key: &[u8], value: &[u8]) -> ChangeEvent {
+        ChangeEvent { kind: ChangeKind::Put, key: key.to_vec(), value: Some(value.to_vec()) }
+    }
+
+    pub(crate) fn delete(key: &[u8]) -> ChangeEvent {
+        ChangeEvent { kind: ChangeKind::Delete, key: key.to_vec(), value: None }
+    }
+
+    /// The events of the operations in `batch`.
+    pub(crate) fn from_batch(batch: &WriteBatch) -> Vec<ChangeEvent> {
+        let mut events = BatchEvents(Vec::new());
+        batch.for_each(&mut events);
+        events.0
+    }
+}
+
+struct BatchEvents(Vec<ChangeEvent>);
+
+impl WriteBatchIterator for BatchEvents {
+    fn put_u8(&mut self, key: &[u8], value: &[u8]) {
+        self.0.push(ChangeEvent::put(key, value));
+    }
+
+    fn deleted_u8(&mut self, key: &[u8]) {
+        self.0.push(ChangeEvent::delete(key));
+    }
+}
+
+/// Selects the events a subscriber receives.
+pub enum ChangeFilter {
+    /// All events.
+    All,
+    /// The events of keys starting with the prefix.
+    Prefix(Vec<u8>),
+    /// The events the function returns `true` for.
+    Predicate(Box<dyn Fn(&ChangeEvent) -> bool + Send>),
+}
+
+impl ChangeFilter {
+    fn matches(&self, event: &ChangeEvent) -> bool {
+        match self {
+            ChangeFilter::All => true,
+            ChangeFilter::Prefix(prefix) => event.key.starts_with(prefix),
+            ChangeFilter::Predicate(predicate) => predicate(event),
+        }
+    }
+}
+
+impl fmt::Debug for ChangeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeFilter::All => f.write_str("All"),
+            ChangeFilter::Prefix(prefix) => f.debug_tuple("Prefix").field(prefix).finish(),
+            ChangeFilter::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    // predicates are only `Send`, the lock lets subscribers be shared
+    filter: Mutex<ChangeFilter>,
+    sender: Sender<ChangeEvent>,
+}
+
+impl Subscriber {
+    // returns false once the receiver is gone
+    fn send(&self, events: &[ChangeEvent]) -> bool {
+        let filter = lock(&self.filter);
+        events.iter()
+            .filter(|event| filter.matches(event))
+            .all(|event| self.sender.send(event.clone()).is_ok())
+    }
+}
+
+/// The subscribers of a database handle and the events waiting to be sent.
+#[derive(Debug, Default)]
+pub(crate) struct Changes {
+    subscribers: Mutex<Vec<Arc<Subscriber>>>,
+    // held while a write is applied and its events are queued
+    writes: Mutex<()>,
+    queue: Mutex<Queue>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    // the events of each write, in the order the writes were applied
+    events: VecDeque<Vec<ChangeEvent>>,
+    // whether a thread is sending the queued events
+    sending: bool,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Changes {
+    // send the queued events, unless another thread is already sending them
+    fn send_queued(&self) {
+        {
+            let mut queue = lock(&self.queue);
+            if queue.sending {
+                return;
+            }
+            queue.sending = true;
+        }
+
+        loop {
+            let events = {
+                let mut queue = lock(&self.queue);
+                match queue.events.pop_front() {
+                    Some(events) => events,
+                    None => {
+                        queue.sending = false;
+                        return;
+                    }
+                }
+            };
+            // a panicking filter must not stop the events of later writes
+            if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| self.send(&events))) {
+                lock(&self.queue).sending = false;
+                panic::resume_unwind(panic);
+            }
+        }
+    }
+
+    fn send(&self, events: &[ChangeEvent]) {
+        let subscribers = lock(&self.subscribers).clone();
+        let gone: Vec<_> = subscribers.iter().filter(|subscriber| !subscriber.send(events)).collect();
+        if !gone.is_empty() {
+            lock(&self.subscribers).retain(|subscriber| !gone.iter().any(|g| Arc::ptr_eq(g, subscriber)));
+        }
+    }
+}
+
+impl Database {
+    /// Receive the changes made through this handle that match `filter`.
+    ///
+    /// The subscription ends when the receiver is dropped. Events queue up
+    /// in the channel until they are received.
+    ///
+    /// A `ChangeFilter::Predicate` runs on a thread writing to the handle,
+    /// after that write was applied. It may use the database.
+    pub fn subscribe(&self, filter: ChangeFilter) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        lock(&self.changes.subscribers).push(Arc::new(Subscriber { filter: Mutex::new(filter), sender }));
+        receiver
+    }
+
+    /// Run `write` and send the events returned by `events` to the subscribers.
+    ///
+    /// `events` is called after `write` succeeded, so it may describe what
+    /// `write` decided to write.
+    ///
+    /// While there are subscribers, writes are applied one at a time, so
+    /// the events are sent in the order the writes were applied.
+    pub(crate) fn publish<W, E>(&self, write: W, events: E) -> Result<(), Error>
+        where W: FnOnce() -> Result<(), Error>, E: FnOnce() -> Vec<ChangeEvent>
+    {
+        if lock(&self.changes.subscribers).is_empty() {
+            return write();
+        }
+
+        {
+            let _writes = lock(&self.changes.writes);
+            write()?;
+            let events = events();
+            lock(&self.changes.queue).events.push_back(events);
+        }
+        self.changes.send_queued();
+
+        Ok(())
+    }
+}
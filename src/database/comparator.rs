@@ -69,12 +69,40 @@ unsafe impl<C: Comparator> InternalComparator for C {}
 
 #[allow(missing_docs)]
 pub fn create_comparator<T: Comparator>(x: Box<T>) -> *mut leveldb_comparator_t {
-    unsafe {
-        leveldb_comparator_create(Box::into_raw(x) as *mut c_void,
+    create_raw_comparator(x).0
+}
+
+/// The compare callback of a comparator handed to leveldb.
+///
+/// It stays valid for as long as the leveldb comparator is alive.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RawCompare {
+    state: *mut c_void,
+    compare: extern "C" fn(*mut c_void, *const c_char, size_t, *const c_char, size_t) -> i32,
+}
+
+impl RawCompare {
+    pub(crate) fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        (self.compare)(self.state,
+                       a.as_ptr() as *const c_char,
+                       a.len() as size_t,
+                       b.as_ptr() as *const c_char,
+                       b.len() as size_t).cmp(&0)
+    }
+}
+
+pub(crate) fn create_raw_comparator<T: Comparator>(x: Box<T>) -> (*mut leveldb_comparator_t, RawCompare) {
+    let state = Box::into_raw(x) as *mut c_void;
+    let compare = RawCompare { state, compare: <T as InternalComparator>::compare };
+
+    let ptr = unsafe {
+        leveldb_comparator_create(state,
                                   <T as InternalComparator>::destructor,
                                   <T as InternalComparator>::compare,
                                   <T as InternalComparator>::name)
-    }
+    };
+
+    (ptr, compare)
 }
 
 impl Comparator for DefaultComparator {
@@ -4,10 +4,21 @@ use libc::{c_char, size_t};
 use super::options::*;
 use super::error::Error;
 use super::bytes::Bytes;
-use super::comparator::{Comparator, create_comparator};
+use super::comparator::{Comparator, RawCompare, create_raw_comparator};
+use super::filter_policy::Filter;
+use super::env::Env;
+use super::logger::Logger;
 use super::key::IntoLevelDBKey;
+use super::transaction::VersionTable;
+use super::atomic::KeyLocks;
+use super::merge::{Merge, get_merged};
+use super::snapshots::Snapshots;
+use super::changes::{ChangeEvent, Changes};
+use super::batch::{Batch, WriteBatch};
+use std::collections::HashMap;
 use std::path::Path;
 use std::ptr;
+use std::sync::Mutex;
 
 #[allow(missing_docs)]
 #[derive(Debug)]
@@ -28,6 +39,7 @@ impl Drop for RawDB {
 #[derive(Debug)]
 pub(crate) struct RawComparator {
     ptr: *mut leveldb_comparator_t,
+    compare: RawCompare,
 }
 
 #[allow(missing_docs)]
@@ -46,22 +58,51 @@ pub struct Database {
     // it is never read from Rust, but must be kept around
     #[allow(dead_code)]
     pub(crate) comparator: Option<RawComparator>,
+    // the filter policy is used by leveldb until the database is closed
+    #[allow(dead_code)]
+    pub(crate) filter_policy: Option<Filter>,
+    // the environment is used by leveldb until the database is closed
+    #[allow(dead_code)]
+    pub(crate) env: Option<Env>,
+    // the info log is written by leveldb until the database is closed
+    #[allow(dead_code)]
+    pub(crate) info_log: Option<Logger>,
+    // ids of the keyspaces used through this handle, by name
+    pub(crate) keyspaces: Mutex<HashMap<String, u32>>,
+    // versions of the keys written by transactions, to detect conflicts
+    pub(crate) versions: Mutex<VersionTable>,
+    // serializes the read-modify-write operations on each key
+    pub(crate) key_locks: KeyLocks,
+    // the merge operator and the order of the operands written through this handle
+    pub(crate) merge: Option<Merge>,
+    // the subscribers to the writes made through this handle and their queued events
+    pub(crate) changes: Changes,
 }
 
 unsafe impl Sync for Database {}
 unsafe impl Send for Database {}
 
 impl Database {
-    fn new(database: *mut leveldb_t, comparator: Option<*mut leveldb_comparator_t>)
+    fn new(database: *mut leveldb_t,
+           options: &Options,
+           comparator: Option<(*mut leveldb_comparator_t, RawCompare)>)
            -> Database {
         let raw_comp = match comparator {
-            Some(p) => Some(RawComparator { ptr: p }),
+            Some((p, compare)) => Some(RawComparator { ptr: p, compare }),
             None => None
         };
 
         Database {
             database: RawDB { ptr: database },
             comparator: raw_comp,
+            filter_policy: options.filter_policy.clone(),
+            env: options.env.clone(),
+            info_log: options.info_log.clone(),
+            keyspaces: Mutex::new(HashMap::new()),
+            versions: Mutex::new(VersionTable::default()),
+            key_locks: KeyLocks::new(),
+            merge: options.merge_operator.clone().map(Merge::new),
+            changes: Changes::default(),
         }
     }
 
@@ -81,7 +122,36 @@ impl Database {
             leveldb_options_destroy(c_options);
 
             if error == ptr::null_mut() {
-                Ok(Database::new(db, None))
+                Ok(Database::new(db, options, None))
+            } else {
+                Err(Error::new_from_char(error))
+            }
+        }
+    }
+
+    /// Open a new database that lives in memory only
+    ///
+    /// The database is always created and uses a fresh in-memory environment,
+    /// overriding `options.create_if_missing` and `options.env`. All data is
+    /// lost once the database is dropped.
+    pub fn open_in_memory(options: &Options) -> Result<Database, Error> {
+        let mut error = ptr::null_mut();
+        let env = Env::in_memory();
+
+        unsafe {
+            let c_string = CString::new("/in-memory").unwrap();
+            let c_options = c_options(options, None);
+            leveldb_options_set_create_if_missing(c_options, 1);
+            leveldb_options_set_env(c_options, env.raw_ptr());
+            let db = leveldb_open(c_options as *const leveldb_options_t,
+                                  c_string.as_bytes_with_nul().as_ptr() as *const c_char,
+                                  &mut error);
+            leveldb_options_destroy(c_options);
+
+            if error.is_null() {
+                let mut database = Database::new(db, options, None);
+                database.env = Some(env);
+                Ok(database)
             } else {
                 Err(Error::new_from_char(error))
             }
@@ -101,7 +171,7 @@ impl Database {
                                                comparator: C)
                                                -> Result<Database, Error> {
         let mut error = ptr::null_mut();
-        let comp_ptr = create_comparator(Box::new(comparator));
+        let (comp_ptr, compare) = create_raw_comparator(Box::new(comparator));
         unsafe {
             let c_string = CString::new(name.to_str().unwrap()).unwrap();
             let c_options = c_options(options, Some(comp_ptr));
@@ -111,13 +181,18 @@ impl Database {
             leveldb_options_destroy(c_options);
 
             if error == ptr::null_mut() {
-                Ok(Database::new(db, Some(comp_ptr)))
+                Ok(Database::new(db, options, Some((comp_ptr, compare))))
             } else {
                 Err(Error::new_from_char(error))
             }
         }
     }
 
+    /// The compare function of a custom comparator, if the database uses one.
+    pub(crate) fn compare_fn(&self) -> Option<RawCompare> {
+        self.comparator.as_ref().map(|c| c.compare)
+    }
+
     pub fn put(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, value: &[u8]) -> Result<(), Error> {
         key.as_u8_slice_for_write(&|k| {
             self.put_u8(options, k, value)
@@ -125,6 +200,15 @@ impl Database {
     }
 
     pub fn put_u8(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        if self.merge.is_some() {
+            let batch = WriteBatch::new();
+            batch.put_u8(key, value);
+            return self.write(options, &batch);
+        }
+        self.publish(|| self.put_raw(options, key, value), || vec![ChangeEvent::put(key, value)])
+    }
+
+    fn put_raw(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<(), Error> {
         unsafe {
             let mut error = ptr::null_mut();
             let c_writeoptions = c_writeoptions(options);
@@ -154,6 +238,11 @@ impl Database {
     }
 
     pub fn get_u8(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(ref merge) = self.merge {
+            // read the value and its operands from the same state
+            let snapshot = self.snapshot();
+            return get_merged(self, snapshot.raw_ptr(), merge, options, key);
+        }
         unsafe {
             let mut error = ptr::null_mut();
             let mut length: size_t = 0;
@@ -176,6 +265,14 @@ impl Database {
         }
     }
 
+    /// fetches several keys from the same implicit snapshot
+    ///
+    /// The results are consistent with each other, as if all keys were
+    /// read at once. Returns one result per key, in the order of `keys`.
+    pub fn multi_get(&self, options: &ReadOptions, keys: &[impl IntoLevelDBKey]) -> Vec<Result<Option<Vec<u8>>, Error>> {
+        self.snapshot().multi_get(options, keys)
+    }
+
     pub fn delete(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey) -> Result<(), Error> {
         key.as_u8_slice_for_write(&|k| {
             self.delete_u8(options, k)
@@ -183,6 +280,15 @@ impl Database {
     }
 
     pub fn delete_u8(&self, options: &WriteOptions, key: &[u8]) -> Result<(), Error> {
+        if self.merge.is_some() {
+            let batch = WriteBatch::new();
+            batch.delete_u8(key);
+            return self.write(options, &batch);
+        }
+        self.publish(|| self.delete_raw(options, key), || vec![ChangeEvent::delete(key)])
+    }
+
+    fn delete_raw(&self, options: &WriteOptions, key: &[u8]) -> Result<(), Error> {
         unsafe {
             let mut error = ptr::null_mut();
             let c_writeoptions = c_writeoptions(&options);
@@ -0,0 +1,273 @@
+//! Order-preserving key encoding
+//!
+//! leveldb's default comparator orders keys bytewise. The plain big-endian
+//! bytes used by `IntoLevelDBKey` only keep the order of unsigned integers:
+//! negative numbers sort after positive ones. The encoding in this module
+//! keeps the natural order of its values under the bytewise comparator:
+//!
+//! * unsigned integers are stored big-endian
+//! * signed integers are stored big-endian with the sign bit flipped
+//! * floats are stored in IEEE-754 total order, `-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN`
+//! * strings and byte strings escape `0x00` as `0x00 0xff` and end with `0x00 0x01`
+//! * tuples are the concatenation of their elements
+//!
+//! Wrap a value in `Ordered` to use it as a key of a `Database`.
+use super::error::Error;
+use super::key::IntoLevelDBKey;
+use super::util::FromU8;
+
+/// Values that can be written in the order-preserving encoding.
+pub trait OrderedEncode {
+    /// Append the encoding of `self` to `out`.
+    fn encode_ordered(&self, out: &mut Vec<u8>);
+}
+
+/// Values that can be read from the order-preserving encoding.
+pub trait OrderedDecode: Sized {
+    /// Decode a value from the start of `data`, advancing it past
+    /// the consumed bytes.
+    fn decode_ordered(data: &mut &[u8]) -> Result<Self, Error>;
+}
+
+/// Encode `value` in the order-preserving encoding.
+pub fn encode<T: OrderedEncode + ?Sized>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode_ordered(&mut out);
+
+    out
+}
+
+/// Decode a value encoded by `encode`, failing if `data` has trailing bytes.
+pub fn decode<T: OrderedDecode>(mut data: &[u8]) -> Result<T, Error> {
+    let value = T::decode_ordered(&mut data)?;
+    if !data.is_empty() {
+        return Err(Error::new(format!("{} trailing bytes after ordered key", data.len())));
+    }
+
+    Ok(value)
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+    if data.len() < len {
+        return Err(Error::new("truncated ordered key".to_string()));
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+
+    Ok(head)
+}
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_NUL: u8 = 0xff;
+const TERMINATOR: u8 = 0x01;
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == ESCAPE {
+            out.push(ESCAPED_NUL);
+        }
+    }
+    out.push(ESCAPE);
+    out.push(TERMINATOR);
+}
+
+fn decode_bytes(data: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+
+    loop {
+        let byte = take(data, 1)?[0];
+        if byte != ESCAPE {
+            bytes.push(byte);
+            continue;
+        }
+        match take(data, 1)?[0] {
+            ESCAPED_NUL => bytes.push(ESCAPE),
+            TERMINATOR => return Ok(bytes),
+            other => return Err(Error::new(format!("invalid escape sequence 0x00 0x{:02x} in ordered key", other))),
+        }
+    }
+}
+
+macro_rules! impl_ordered_for_unsigned {
+    ($T: ty) => {
+        impl OrderedEncode for $T {
+            fn encode_ordered(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+        }
+
+        impl OrderedDecode for $T {
+            fn decode_ordered(data: &mut &[u8]) -> Result<$T, Error> {
+                let mut bytes = [0u8; std::mem::size_of::<$T>()];
+                bytes.copy_from_slice(take(data, std::mem::size_of::<$T>())?);
+
+                Ok(<$T>::from_be_bytes(bytes))
+            }
+        }
+    };
+}
+
+macro_rules! impl_ordered_for_signed {
+    ($T: ty, $U: ty) => {
+        impl OrderedEncode for $T {
+            fn encode_ordered(&self, out: &mut Vec<u8>) {
+                ((*self as $U) ^ (<$T>::MIN as $U)).encode_ordered(out);
+            }
+        }
+
+        impl OrderedDecode for $T {
+            fn decode_ordered(data: &mut &[u8]) -> Result<$T, Error> {
+                Ok((<$U>::decode_ordered(data)? ^ (<$T>::MIN as $U)) as $T)
+            }
+        }
+    };
+}
+
+macro_rules! impl_ordered_for_float {
+    ($T: ty, $U: ty) => {
+        impl OrderedEncode for $T {
+            fn encode_ordered(&self, out: &mut Vec<u8>) {
+                let bits = self.to_bits();
+                let sign = 1 << (<$U>::BITS - 1);
+                // negative numbers are inverted, so larger magnitudes sort first
+                let bits = if bits & sign != 0 { !bits } else { bits | sign };
+                bits.encode_ordered(out);
+            }
+        }
+
+        impl OrderedDecode for $T {
+            fn decode_ordered(data: &mut &[u8]) -> Result<$T, Error> {
+                let bits = <$U>::decode_ordered(data)?;
+                let sign = 1 << (<$U>::BITS - 1);
+                let bits = if bits & sign != 0 { bits & !sign } else { !bits };
+
+                Ok(<$T>::from_bits(bits))
+            }
+        }
+    };
+}
+
+impl_ordered_for_unsigned!(u8);
+impl_ordered_for_unsigned!(u16);
+impl_ordered_for_unsigned!(u32);
+impl_ordered_for_unsigned!(u64);
+impl_ordered_for_unsigned!(u128);
+impl_ordered_for_signed!(i8, u8);
+impl_ordered_for_signed!(i16, u16);
+impl_ordered_for_signed!(i32, u32);
+impl_ordered_for_signed!(i64, u64);
+impl_ordered_for_signed!(i128, u128);
+impl_ordered_for_float!(f32, u32);
+impl_ordered_for_float!(f64, u64);
+
+impl OrderedEncode for bool {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl OrderedDecode for bool {
+    fn decode_ordered(data: &mut &[u8]) -> Result<bool, Error> {
+        match take(data, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(Error::new(format!("invalid bool 0x{:02x} in ordered key", other))),
+        }
+    }
+}
+
+impl OrderedEncode for [u8] {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        encode_bytes(self, out);
+    }
+}
+
+impl OrderedEncode for Vec<u8> {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        encode_bytes(self, out);
+    }
+}
+
+impl OrderedDecode for Vec<u8> {
+    fn decode_ordered(data: &mut &[u8]) -> Result<Vec<u8>, Error> {
+        decode_bytes(data)
+    }
+}
+
+impl OrderedEncode for str {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out);
+    }
+}
+
+impl OrderedEncode for String {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out);
+    }
+}
+
+impl OrderedDecode for String {
+    fn decode_ordered(data: &mut &[u8]) -> Result<String, Error> {
+        String::from_utf8(decode_bytes(data)?).map_err(|e| Error::new(format!("invalid utf-8 in ordered key: {}", e)))
+    }
+}
+
+impl<T: OrderedEncode + ?Sized> OrderedEncode for &T {
+    fn encode_ordered(&self, out: &mut Vec<u8>) {
+        (**self).encode_ordered(out);
+    }
+}
+
+macro_rules! impl_ordered_for_tuple {
+    ($($T: ident),+) => {
+        impl<$($T: OrderedEncode),+> OrderedEncode for ($($T,)+) {
+            #[allow(non_snake_case)]
+            fn encode_ordered(&self, out: &mut Vec<u8>) {
+                let ($($T,)+) = self;
+                $($T.encode_ordered(out);)+
+            }
+        }
+
+        impl<$($T: OrderedDecode),+> OrderedDecode for ($($T,)+) {
+            fn decode_ordered(data: &mut &[u8]) -> Result<($($T,)+), Error> {
+                Ok(($($T::decode_ordered(data)?,)+))
+            }
+        }
+    };
+}
+
+impl_ordered_for_tuple!(A);
+impl_ordered_for_tuple!(A, B);
+impl_ordered_for_tuple!(A, B, C);
+impl_ordered_for_tuple!(A, B, C, D);
+impl_ordered_for_tuple!(A, B, C, D, E);
+impl_ordered_for_tuple!(A, B, C, D, E, F);
+
+/// A key stored in the order-preserving encoding.
+///
+/// `Ordered(-1i32)` sorts before `Ordered(1i32)`, and
+/// `Ordered(("user", 7u32))` before `Ordered(("user", 12u32))`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ordered<T>(pub T);
+
+impl<T: OrderedEncode> IntoLevelDBKey for Ordered<T> {
+    fn as_u8_slice_for_write(&self, f: &dyn Fn(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+        f(&encode(&self.0))
+    }
+
+    fn as_u8_slice_for_get(&self, f: &dyn Fn(&[u8]) ->  Result<Option<Vec<u8>>, Error>) ->  Result<Option<Vec<u8>>, Error> {
+        f(&encode(&self.0))
+    }
+}
+
+impl<T: OrderedDecode> FromU8 for Ordered<T> {
+    /// Panics if `data` is not a valid encoding of `T`, use `decode` to
+    /// handle invalid keys.
+    fn from_u8(data: &[u8]) -> Ordered<T> {
+        match decode(data) {
+            Ok(value) => Ordered(value),
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Structs to work with the leveldb environment.
+//!
+//! The environment is used by leveldb to access the file system and
+//! to schedule background work.
+use leveldb_sys::{leveldb_env_t, leveldb_create_default_env, leveldb_env_destroy};
+use std::sync::Arc;
+
+extern "C" {
+    // from shim/leveldb_shim.cc, wraps `leveldb::NewMemEnv`
+    fn rs_leveldb_create_mem_env() -> *mut leveldb_env_t;
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+struct RawEnv {
+    ptr: *mut leveldb_env_t,
+}
+
+unsafe impl Sync for RawEnv {}
+unsafe impl Send for RawEnv {}
+
+impl Drop for RawEnv {
+    fn drop(&mut self) {
+        unsafe {
+            leveldb_env_destroy(self.ptr);
+        }
+    }
+}
+
+/// Represents a leveldb environment
+///
+/// Databases opened with an environment keep a handle to it, so it stays
+/// alive for as long as any database uses it.
+#[derive(Clone, Debug)]
+pub struct Env {
+    raw: Arc<RawEnv>,
+}
+
+impl Env {
+    /// Return the default environment, backed by the file system.
+    pub fn new() -> Env {
+        let env = unsafe { leveldb_create_default_env() };
+        Env { raw: Arc::new(RawEnv { ptr: env }) }
+    }
+
+    /// Create an environment keeping all files in memory.
+    ///
+    /// Every in-memory environment is independent of all others. Its contents
+    /// are lost once the environment is dropped.
+    pub fn in_memory() -> Env {
+        let env = unsafe { rs_leveldb_create_mem_env() };
+        Env { raw: Arc::new(RawEnv { ptr: env }) }
+    }
+
+    #[allow(missing_docs)]
+    pub fn raw_ptr(&self) -> *mut leveldb_env_t {
+        self.raw.ptr
+    }
+}
+
+impl Default for Env {
+    fn default() -> Env {
+        Env::new()
+    }
+}
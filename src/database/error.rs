@@ -4,17 +4,37 @@ use libc::{c_void, c_char};
 use leveldb_sys::leveldb_free;
 use std;
 
+/// The kind of an error, for errors callers may want to handle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A transaction read a key that was changed by a concurrent commit.
+    Conflict,
+    /// Any other error, e.g. one reported by leveldb.
+    Other,
+}
+
 /// A leveldb error, just containing the error string
 /// provided by leveldb.
 #[derive(Debug)]
 pub struct Error {
     message: String,
+    kind: ErrorKind,
 }
 
 impl Error {
     /// create a new Error, using the String provided
     pub fn new(message: String) -> Error {
-        Error { message: message }
+        Error { message, kind: ErrorKind::Other }
+    }
+
+    /// create a new Error of the given kind
+    pub fn with_kind(kind: ErrorKind, message: String) -> Error {
+        Error { message, kind }
+    }
+
+    /// The kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
     }
 
     /// create an error from a c-string buffer.
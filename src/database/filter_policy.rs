@@ -0,0 +1,160 @@
+//! Filter policies reduce the number of disk reads for keys that are
+//! not in the database.
+//!
+//! leveldb stores a filter for every block of a table and consults it
+//! before reading the block. A bloom filter is built in, custom filters can
+//! be provided by implementing `FilterPolicy`.
+use leveldb_sys::*;
+use libc::{size_t, c_void, c_char, c_int, c_uchar};
+use std::slice;
+use std::ptr;
+use std::sync::Arc;
+
+extern "C" {
+    // not exposed by leveldb-sys, but part of the leveldb C API
+    fn leveldb_filterpolicy_create(
+        state: *mut c_void,
+        destructor: extern "C" fn(*mut c_void),
+        create_filter: extern "C" fn(*mut c_void, *const *const c_char, *const size_t, c_int, *mut size_t) -> *mut c_char,
+        key_may_match: extern "C" fn(*mut c_void, *const c_char, size_t, *const c_char, size_t) -> c_uchar,
+        name: extern "C" fn(*mut c_void) -> *const c_char
+    ) -> *mut leveldb_filterpolicy_t;
+}
+
+/// A filter policy has three important functions:
+///
+/// * the name function returns a fixed name. Changing the filter encoding
+///   requires changing the name, as filters are persisted with the tables.
+/// * `create_filter` builds a filter summarizing a set of keys
+/// * `key_may_match` tests a key against a filter. It must return `true`
+///   for every key that was passed to `create_filter`.
+pub trait FilterPolicy {
+    /// Return the name of the filter policy, as a nul-terminated string.
+    fn name(&self) -> *const c_char;
+    /// Build a filter for the given keys.
+    fn create_filter(&self, keys: &[&[u8]]) -> Vec<u8>;
+    /// Return `false` only if `key` was definitely not passed to the
+    /// `create_filter` call that produced `filter`.
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool;
+}
+
+/// # Safety
+///
+/// The callbacks must only be registered with a `state` pointing to a boxed `Self`.
+unsafe trait InternalFilterPolicy: FilterPolicy where Self: Sized {
+    extern "C" fn name(state: *mut c_void) -> *const c_char {
+        let x = unsafe { &*(state as *mut Self) };
+        x.name()
+    }
+
+    extern "C" fn create_filter(
+        state: *mut c_void,
+        key_array: *const *const c_char,
+        key_length_array: *const size_t,
+        num_keys: c_int,
+        filter_length: *mut size_t
+    ) -> *mut c_char {
+        unsafe {
+            let x = &*(state as *mut Self);
+            let key_ptrs = slice::from_raw_parts(key_array, num_keys as usize);
+            let key_lens = slice::from_raw_parts(key_length_array, num_keys as usize);
+            let keys: Vec<&[u8]> = key_ptrs.iter().zip(key_lens)
+                .map(|(k, len)| slice::from_raw_parts(*k as *const u8, *len))
+                .collect();
+
+            let filter = x.create_filter(&keys);
+
+            // leveldb releases the filter using `free`
+            let result = libc::malloc(filter.len()) as *mut c_char;
+            if !filter.is_empty() {
+                ptr::copy_nonoverlapping(filter.as_ptr() as *const c_char, result, filter.len());
+            }
+            *filter_length = filter.len();
+
+            result
+        }
+    }
+
+    extern "C" fn key_may_match(
+        state: *mut c_void,
+        key: *const c_char,
+        length: size_t,
+        filter: *const c_char,
+        filter_length: size_t
+    ) -> c_uchar {
+        unsafe {
+            let x = &*(state as *mut Self);
+            let key_slice = slice::from_raw_parts(key as *const u8, length);
+            let filter_slice = slice::from_raw_parts(filter as *const u8, filter_length);
+
+            x.key_may_match(key_slice, filter_slice) as c_uchar
+        }
+    }
+
+    extern "C" fn destructor(state: *mut c_void) {
+        let _x: Box<Self> = unsafe { Box::from_raw(state as *mut Self) };
+        // let the Box fall out of scope and run the T's destructor
+    }
+}
+
+unsafe impl<F: FilterPolicy> InternalFilterPolicy for F {}
+
+#[allow(missing_docs)]
+pub fn create_filter_policy<T: FilterPolicy>(x: Box<T>) -> *mut leveldb_filterpolicy_t {
+    unsafe {
+        leveldb_filterpolicy_create(Box::into_raw(x) as *mut c_void,
+                                    <T as InternalFilterPolicy>::destructor,
+                                    <T as InternalFilterPolicy>::create_filter,
+                                    <T as InternalFilterPolicy>::key_may_match,
+                                    <T as InternalFilterPolicy>::name)
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+struct RawFilterPolicy {
+    ptr: *mut leveldb_filterpolicy_t,
+}
+
+unsafe impl Sync for RawFilterPolicy {}
+unsafe impl Send for RawFilterPolicy {}
+
+impl Drop for RawFilterPolicy {
+    fn drop(&mut self) {
+        unsafe {
+            leveldb_filterpolicy_destroy(self.ptr);
+        }
+    }
+}
+
+/// Represents a leveldb filter policy
+///
+/// Databases opened with a filter keep a handle to it, so it stays
+/// alive for as long as any database uses it.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    raw: Arc<RawFilterPolicy>,
+}
+
+
+impl Filter {
+    /// Create leveldb's built-in bloom filter, using `bits_per_key` bits
+    /// per key. A good value is 10, which yields ~1% false positives.
+    pub fn bloom(bits_per_key: i32) -> Filter {
+        let ptr = unsafe { leveldb_filterpolicy_create_bloom(bits_per_key) };
+        Filter { raw: Arc::new(RawFilterPolicy { ptr }) }
+    }
+
+    /// Create a filter from a custom `FilterPolicy`.
+    ///
+    /// leveldb calls the policy from its background compaction thread.
+    pub fn new<T: FilterPolicy + Send + Sync>(policy: T) -> Filter {
+        let ptr = create_filter_policy(Box::new(policy));
+        Filter { raw: Arc::new(RawFilterPolicy { ptr }) }
+    }
+
+    #[allow(missing_docs)]
+    pub fn raw_ptr(&self) -> *mut leveldb_filterpolicy_t {
+        self.raw.ptr
+    }
+}
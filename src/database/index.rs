@@ -0,0 +1,273 @@
+//! Secondary indexes
+//!
+//! An `IndexedDatabase` keeps secondary indexes of the entries written
+//! through it. Each index is named and uses an `Indexer` to map an entry to
+//! its index values. On every `put` and `delete`, the index entries are
+//! updated in the same `WriteBatch` as the entry itself.
+//!
+//! Index entries are stored below `INDEX_PREFIX`. Entries written to the
+//! database directly are not indexed until the index is rebuilt. Lookups
+//! check each entry found against its current value, so they skip the
+//! stale index entries such writes leave behind.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::{IntoLevelDBKey, key_to_vec};
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, LevelDBIterator, OwnedIterable, OwnedIterator};
+use super::snapshots::ArcSnapshot;
+use super::encoding;
+use super::keyspace::KEYSPACE_PREFIX;
+use super::merge::MERGE_PREFIX;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// The prefix reserved for index entries.
+pub const INDEX_PREFIX: &[u8] = b"\x00__index__\x00";
+
+// the number of index entries written in one batch by `rebuild_index`
+const REBUILD_BATCH_SIZE: usize = 1000;
+
+/// Maps an entry to the values it is found under in an index.
+pub trait Indexer: Send + Sync {
+    /// The index values of `key` with `value`, possibly none.
+    fn index_values(&self, key: &[u8], value: &[u8]) -> Vec<Vec<u8>>;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> Vec<Vec<u8>> + Send + Sync> Indexer for F {
+    fn index_values(&self, key: &[u8], value: &[u8]) -> Vec<Vec<u8>> {
+        self(key, value)
+    }
+}
+
+// the entries of index `name` for `index_value` start with this prefix,
+// followed by the primary key
+fn value_prefix(name: &str, index_value: &[u8]) -> Vec<u8> {
+    let mut prefix = index_prefix(name);
+    prefix.extend_from_slice(&encoding::encode(index_value));
+    prefix
+}
+
+fn index_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = INDEX_PREFIX.to_vec();
+    prefix.extend_from_slice(&encoding::encode(name));
+    prefix
+}
+
+fn is_reserved(key: &[u8]) -> bool {
+    [INDEX_PREFIX, KEYSPACE_PREFIX, MERGE_PREFIX].iter().any(|prefix| key.starts_with(prefix))
+}
+
+struct Index {
+    name: String,
+    indexer: Arc<dyn Indexer>,
+}
+
+impl Index {
+    // the database keys of the index entries of `key` with `value`
+    fn entries(&self, key: &[u8], value: &[u8]) -> BTreeSet<Vec<u8>> {
+        self.indexer.index_values(key, value).iter()
+            .map(|index_value| {
+                let mut entry = value_prefix(&self.name, index_value);
+                entry.extend_from_slice(key);
+                entry
+            })
+            .collect()
+    }
+}
+
+/// A database maintaining secondary indexes
+pub struct IndexedDatabase {
+    database: Arc<Database>,
+    indexes: Vec<Index>,
+}
+
+impl IndexedDatabase {
+    /// Wrap `database`, without indexes.
+    pub fn new(database: Arc<Database>) -> IndexedDatabase {
+        IndexedDatabase { database, indexes: Vec::new() }
+    }
+
+    /// The underlying database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+
+    /// Maintain the index `name` with `indexer`.
+    ///
+    /// Fails if an index of that name was already added. Entries written
+    /// before the index was added are found once it is rebuilt.
+    pub fn add_index<I: Indexer + 'static>(&mut self, name: &str, indexer: I) -> Result<(), Error> {
+        if self.indexes.iter().any(|index| index.name == name) {
+            return Err(Error::new(format!("index {} already exists", name)));
+        }
+        self.indexes.push(Index { name: name.to_string(), indexer: Arc::new(indexer) });
+
+        Ok(())
+    }
+
+    /// The names of the indexes, in the order they were added.
+    pub fn index_names(&self) -> Vec<&str> {
+        self.indexes.iter().map(|index| index.name.as_str()).collect()
+    }
+
+    fn index(&self, name: &str) -> Result<&Index, Error> {
+        self.indexes.iter()
+            .find(|index| index.name == name)
+            .ok_or_else(|| Error::new(format!("no index named {}", name)))
+    }
+
+    /// Store `value` under `key` and update its index entries.
+    pub fn put(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, value: &[u8]) -> Result<(), Error> {
+        self.write_u8(options, &key_to_vec(key), Some(value))
+    }
+
+    pub fn put_u8(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.write_u8(options, key, Some(value))
+    }
+
+    /// Delete `key` and its index entries.
+    pub fn delete(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey) -> Result<(), Error> {
+        self.write_u8(options, &key_to_vec(key), None)
+    }
+
+    pub fn delete_u8(&self, options: &WriteOptions, key: &[u8]) -> Result<(), Error> {
+        self.write_u8(options, key, None)
+    }
+
+    fn write_u8(&self, options: &WriteOptions, key: &[u8], value: Option<&[u8]>) -> Result<(), Error> {
+        // the old value decides which entries to remove, keep it from changing
+        let _guard = self.database.key_locks.lock(key);
+        let old = self.database.get_u8(&ReadOptions::new(), key)?;
+
+        let batch = WriteBatch::new();
+        for index in &self.indexes {
+            let old_entries = old.as_ref().map(|old| index.entries(key, old)).unwrap_or_default();
+            let new_entries = value.map(|value| index.entries(key, value)).unwrap_or_default();
+
+            for entry in old_entries.difference(&new_entries) {
+                batch.delete_u8(entry);
+            }
+            for entry in new_entries.difference(&old_entries) {
+                batch.put_u8(entry, &[]);
+            }
+        }
+        match value {
+            Some(value) => batch.put_u8(key, value),
+            None => batch.delete_u8(key),
+        }
+
+        self.database.write(options, &batch)
+    }
+
+    /// fetches a key
+    pub fn get(&self, options: &ReadOptions, key: &dyn IntoLevelDBKey) -> Result<Option<Vec<u8>>, Error> {
+        self.database.get(options, key)
+    }
+
+    pub fn get_u8(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.database.get_u8(options, key)
+    }
+
+    /// Return an Iterator over the (Key,Value) pairs whose entry in the
+    /// index `name` has `index_value`, ordered by key.
+    ///
+    /// Index entries and values are read from the same snapshot. Entries
+    /// whose value no longer has `index_value` are skipped.
+    pub fn lookup_by_index(&self, options: &ReadOptions, name: &str, index_value: &[u8]) -> Result<IndexIterator, Error> {
+        let index = self.index(name)?;
+        let prefix = value_prefix(&index.name, index_value);
+        let snapshot = ArcSnapshot::new(self.database.clone());
+        let entries = snapshot.owned_prefix_iter(options, &prefix);
+
+        Ok(IndexIterator {
+            entries,
+            snapshot,
+            options: *options,
+            prefix_len: prefix.len(),
+            indexer: index.indexer.clone(),
+            index_value: index_value.to_vec(),
+        })
+    }
+
+    /// Regenerate the index `name` from a full scan of the database.
+    ///
+    /// Keys below the reserved prefixes of indexes, keyspaces and merge
+    /// operands are not indexed. Entries must not be written while the
+    /// index is rebuilt. Returns the number of index entries written.
+    pub fn rebuild_index(&self, options: &WriteOptions, name: &str) -> Result<u64, Error> {
+        let index = self.index(name)?;
+        let read_opts = ReadOptions::new();
+
+        let batch = WriteBatch::new();
+        let mut batched = 0;
+        for entry in self.database.prefix_keys_iter(&read_opts, &index_prefix(name)).fallible() {
+            batch.delete_u8(&entry?);
+            batched += 1;
+            if batched == REBUILD_BATCH_SIZE {
+                self.database.write(options, &batch)?;
+                batch.clear();
+                batched = 0;
+            }
+        }
+
+        let mut written = 0;
+        for entry in self.database.iter(&read_opts).fallible() {
+            let (key, value) = entry?;
+            if is_reserved(&key) {
+                continue;
+            }
+            for entry in index.entries(&key, &value) {
+                batch.put_u8(&entry, &[]);
+                batched += 1;
+                written += 1;
+            }
+            if batched >= REBUILD_BATCH_SIZE {
+                self.database.write(options, &batch)?;
+                batch.clear();
+                batched = 0;
+            }
+        }
+        if batched > 0 {
+            self.database.write(options, &batch)?;
+        }
+
+        Ok(written)
+    }
+}
+
+/// An iterator over the (Key,Value) pairs found through an index
+pub struct IndexIterator {
+    entries: OwnedIterator,
+    snapshot: ArcSnapshot,
+    options: ReadOptions,
+    prefix_len: usize,
+    indexer: Arc<dyn Indexer>,
+    index_value: Vec<u8>,
+}
+
+impl Iterator for IndexIterator {
+    type Item = Result<(Vec<u8>, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (entry, _) = self.entries.next()?;
+            let key = entry[self.prefix_len..].to_vec();
+            match self.snapshot.get_u8(&self.options, &key) {
+                // entries written directly can leave stale index entries behind
+                Ok(Some(value)) if self.indexer.index_values(&key, &value).contains(&self.index_value) => {
+                    return Some(Ok((key, value)));
+                }
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl IndexIterator {
+    /// Return the error leveldb encountered while iterating, if any.
+    pub fn status(&self) -> Result<(), Error> {
+        self.entries.status()
+    }
+}
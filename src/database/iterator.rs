@@ -5,11 +5,18 @@
 use leveldb_sys::*;
 use libc::{size_t, c_char};
 use std::iter;
+use std::ptr;
 use super::Database;
+use super::error::Error;
 use super::options::{ReadOptions, c_readoptions};
 use std::slice::from_raw_parts;
 use std::marker::PhantomData;
-use crate::database::snapshots::Snapshot;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+use crate::database::snapshots::{Snapshot, ArcSnapshot};
+use std::sync::Arc;
+use super::comparator::RawCompare;
+use super::key::{IntoLevelDBKey, key_to_vec};
 
 #[allow(missing_docs)]
 struct RawIterator {
@@ -23,6 +30,209 @@ impl Drop for RawIterator {
     }
 }
 
+/// The range of keys an iterator is restricted to.
+#[derive(Clone)]
+pub(crate) struct KeyRange {
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    // the comparator of the database, if it is not bytewise
+    compare: Option<RawCompare>,
+    // restricts a range of a custom comparator to the keys starting with it
+    prefix: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    pub(crate) fn all() -> KeyRange {
+        KeyRange { start: Bound::Unbounded, end: Bound::Unbounded, compare: None, prefix: None }
+    }
+
+    pub(crate) fn new<K: IntoLevelDBKey, R: RangeBounds<K>>(range: &R, compare: Option<RawCompare>) -> KeyRange {
+        let to_vec = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(key_to_vec(key)),
+            Bound::Excluded(key) => Bound::Excluded(key_to_vec(key)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        KeyRange {
+            start: to_vec(range.start_bound()),
+            end: to_vec(range.end_bound()),
+            compare,
+            prefix: None,
+        }
+    }
+
+    /// The range of all keys starting with `prefix`.
+    ///
+    /// A custom comparator has no bounds for a prefix, its keys are found
+    /// by stepping from the position of `prefix` to either end of them.
+    pub(crate) fn prefix(prefix: &[u8], compare: Option<RawCompare>) -> KeyRange {
+        if compare.is_some() {
+            return KeyRange { prefix: Some(prefix.to_vec()), compare, ..KeyRange::all() };
+        }
+        // the first key after all keys starting with prefix: drop trailing
+        // 0xff bytes and increment the last remaining one
+        let mut end = prefix.to_vec();
+        while end.last() == Some(&0xff) {
+            end.pop();
+        }
+        let end = match end.last_mut() {
+            Some(last) => {
+                *last += 1;
+                Bound::Excluded(end)
+            }
+            None => Bound::Unbounded,
+        };
+
+        KeyRange { start: Bound::Included(prefix.to_vec()), end, compare: None, prefix: None }
+    }
+
+    /// The keys within `range`, each prefixed with `prefix`, in bytewise order.
+    pub(crate) fn within_prefix<K: IntoLevelDBKey, R: RangeBounds<K>>(prefix: &[u8], range: &R) -> KeyRange {
+        let prefixed = |key: &K| {
+            let mut bytes = prefix.to_vec();
+            bytes.extend_from_slice(&key_to_vec(key));
+            bytes
+        };
+        let all = KeyRange::prefix(prefix, None);
+
+        KeyRange {
+            start: match range.start_bound() {
+                Bound::Included(key) => Bound::Included(prefixed(key)),
+                Bound::Excluded(key) => Bound::Excluded(prefixed(key)),
+                Bound::Unbounded => all.start,
+            },
+            end: match range.end_bound() {
+                Bound::Included(key) => Bound::Included(prefixed(key)),
+                Bound::Excluded(key) => Bound::Excluded(prefixed(key)),
+                Bound::Unbounded => all.end,
+            },
+            compare: None,
+            prefix: None,
+        }
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self.compare {
+            Some(ref compare) => compare.compare(a, b),
+            None => a.cmp(b),
+        }
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        if let Some(ref prefix) = self.prefix {
+            return key.starts_with(prefix);
+        }
+        let after_start = match self.start {
+            Bound::Included(ref start) => self.compare(key, start) != Ordering::Less,
+            Bound::Excluded(ref start) => self.compare(key, start) == Ordering::Greater,
+            Bound::Unbounded => true,
+        };
+        let before_end = match self.end {
+            Bound::Included(ref end) => self.compare(key, end) != Ordering::Greater,
+            Bound::Excluded(ref end) => self.compare(key, end) == Ordering::Less,
+            Bound::Unbounded => true,
+        };
+
+        after_start && before_end
+    }
+
+    // positions `iter` at the key of the prefix that comes first, or last
+    unsafe fn seek_prefix_end(&self, iter: *mut leveldb_iterator_t, prefix: &[u8], first: bool) {
+        let in_prefix = |iter: *mut leveldb_iterator_t| leveldb_iter_valid(iter) != 0 && raw_key(iter).starts_with(prefix);
+        // the keys of the prefix are adjacent, and `prefix` sorts among them
+        // or right next to them
+        raw_seek(iter, prefix);
+        if leveldb_iter_valid(iter) == 0 {
+            leveldb_iter_seek_to_last(iter);
+        }
+        if !in_prefix(iter) {
+            leveldb_iter_prev(iter);
+            if !in_prefix(iter) {
+                // no key has the prefix
+                return;
+            }
+        }
+
+        let step = if first { leveldb_iter_prev } else { leveldb_iter_next };
+        while in_prefix(iter) {
+            step(iter);
+        }
+        match (leveldb_iter_valid(iter) != 0, first) {
+            (true, true) => leveldb_iter_next(iter),
+            (true, false) => leveldb_iter_prev(iter),
+            (false, true) => leveldb_iter_seek_to_first(iter),
+            (false, false) => leveldb_iter_seek_to_last(iter),
+        }
+    }
+
+    unsafe fn seek_to_first(&self, iter: *mut leveldb_iterator_t) {
+        if let Some(ref prefix) = self.prefix {
+            return self.seek_prefix_end(iter, prefix, true);
+        }
+        match self.start {
+            Bound::Included(ref start) => raw_seek(iter, start),
+            Bound::Excluded(ref start) => {
+                raw_seek(iter, start);
+                if leveldb_iter_valid(iter) != 0 && self.compare(raw_key(iter), start) == Ordering::Equal {
+                    leveldb_iter_next(iter);
+                }
+            }
+            Bound::Unbounded => leveldb_iter_seek_to_first(iter),
+        }
+    }
+
+    unsafe fn seek_to_last(&self, iter: *mut leveldb_iterator_t) {
+        if let Some(ref prefix) = self.prefix {
+            return self.seek_prefix_end(iter, prefix, false);
+        }
+        let (end, inclusive) = match self.end {
+            Bound::Included(ref end) => (end, true),
+            Bound::Excluded(ref end) => (end, false),
+            Bound::Unbounded => return leveldb_iter_seek_to_last(iter),
+        };
+
+        // position at the first key >= end, then step back if it is out of range
+        raw_seek(iter, end);
+        if leveldb_iter_valid(iter) == 0 {
+            leveldb_iter_seek_to_last(iter);
+        } else {
+            match self.compare(raw_key(iter), end) {
+                Ordering::Greater => leveldb_iter_prev(iter),
+                Ordering::Equal if !inclusive => leveldb_iter_prev(iter),
+                _ => {}
+            }
+        }
+    }
+}
+
+unsafe fn raw_seek(iter: *mut leveldb_iterator_t, key: &[u8]) {
+    leveldb_iter_seek(iter, key.as_ptr() as *mut c_char, key.len() as size_t);
+}
+
+unsafe fn raw_key<'i>(iter: *mut leveldb_iterator_t) -> &'i [u8] {
+    let length: size_t = 0;
+    let key = leveldb_iter_key(iter, &length) as *const u8;
+    from_raw_parts(key, length as usize)
+}
+
+unsafe fn raw_status(iter: *mut leveldb_iterator_t) -> Result<(), Error> {
+    let mut error: *const c_char = ptr::null();
+    // leveldb writes the error through the pointer, despite its const signature
+    leveldb_iter_get_error(iter, ptr::addr_of_mut!(error) as *const *const c_char);
+
+    if error.is_null() {
+        Ok(())
+    } else {
+        Err(Error::new_from_char(error))
+    }
+}
+
+unsafe fn raw_value<'i>(iter: *mut leveldb_iterator_t) -> &'i [u8] {
+    let length: size_t = 0;
+    let value = leveldb_iter_value(iter, &length) as *const u8;
+    from_raw_parts(value, length as usize)
+}
+
 /// An iterator over the leveldb keyspace.
 ///
 /// Returns key and value as a tuple.
@@ -32,7 +242,11 @@ pub struct Iterator<'a> {
     // but needs to hold the reference for lifetime tracking
     #[allow(dead_code)]
     database: PhantomData<&'a Database>,
-
+    range: KeyRange,
+    // keeps the database alive for iterators that do not borrow it,
+    // declared last so the leveldb iterator is destroyed first
+    #[allow(dead_code)]
+    owner: Option<Arc<dyn Send + Sync>>,
 }
 
 /// An iterator over the leveldb keyspace  that browses the keys backwards.
@@ -44,6 +258,11 @@ pub struct RevIterator<'a> {
     // but needs to hold the reference for lifetime tracking
     #[allow(dead_code)]
     database: PhantomData<&'a Database>,
+    range: KeyRange,
+    // keeps the database alive for iterators that do not borrow it,
+    // declared last so the leveldb iterator is destroyed first
+    #[allow(dead_code)]
+    owner: Option<Arc<dyn Send + Sync>>,
 }
 
 /// An iterator over the leveldb keyspace.
@@ -74,6 +293,36 @@ pub struct RevValueIterator<'a> {
     inner: RevIterator<'a>,
 }
 
+/// An iterator that keeps its database alive instead of borrowing it.
+///
+/// It can be stored, returned and sent to other threads.
+pub type OwnedIterator = Iterator<'static>;
+
+/// A cursor over the leveldb keyspace that gives access to keys and values
+/// without copying them.
+///
+/// Keys and values are borrowed from leveldb's internal buffers. Moving
+/// the cursor requires a mutable borrow, so they stay valid until the next move.
+pub struct RawCursor<'a> {
+    iter: RawIterator,
+    // RawCursor accesses the Database through a leveldb_iter_t pointer
+    // but needs to hold the reference for lifetime tracking
+    #[allow(dead_code)]
+    database: PhantomData<&'a Database>,
+    range: KeyRange,
+    // keeps the database alive for iterators that do not borrow it,
+    // declared last so the leveldb iterator is destroyed first
+    #[allow(dead_code)]
+    owner: Option<Arc<dyn Send + Sync>>,
+}
+
+/// An iterator yielding the items of another iterator as `Ok` values,
+/// followed by an `Err` if leveldb stopped iterating because of an error.
+pub struct FallibleIterator<I> {
+    inner: I,
+    done: bool,
+}
+
 /// A trait to allow access to the three main iteration styles of leveldb.
 pub trait Iterable<'a> {
     /// Return an Iterator iterating over (Key,Value) pairs
@@ -82,6 +331,23 @@ pub trait Iterable<'a> {
     fn keys_iter(&'a self, options: &ReadOptions) -> KeyIterator<'a>;
     /// Returns an Iterator iterating over Values only.
     fn value_iter(&'a self, options: &ReadOptions) -> ValueIterator<'a>;
+    /// Returns an Iterator iterating over the (Key,Value) pairs within `range`.
+    ///
+    /// The iterator ends at the bounds of the range, both when iterating
+    /// forward and in reverse. Seeking outside of the range invalidates it.
+    fn range<K: IntoLevelDBKey, R: RangeBounds<K>>(&'a self, options: &ReadOptions, range: R) -> Iterator<'a>;
+    /// Returns an Iterator iterating over the (Key,Value) pairs whose keys start with `prefix`.
+    ///
+    /// Keys are matched bytewise, so a custom comparator must keep keys
+    /// sharing a prefix adjacent. With a custom comparator, positioning the
+    /// iterator at either end of the prefix steps over its keys.
+    fn prefix_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> Iterator<'a>;
+    /// Returns an Iterator iterating over the Keys starting with `prefix`.
+    fn prefix_keys_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> KeyIterator<'a>;
+    /// Returns an Iterator iterating over the Values of keys starting with `prefix`.
+    fn prefix_value_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> ValueIterator<'a>;
+    /// Returns a cursor positioned at the first key, for iteration without copying.
+    fn cursor(&'a self, options: &ReadOptions) -> RawCursor<'a>;
 }
 
 impl<'a> Iterable<'a> for Database {
@@ -96,6 +362,93 @@ impl<'a> Iterable<'a> for Database {
     fn value_iter(&'a self, options: &ReadOptions) -> ValueIterator<'a> {
         ValueIterator::new(self, options, None)
     }
+
+    fn range<K: IntoLevelDBKey, R: RangeBounds<K>>(&'a self, options: &ReadOptions, range: R) -> Iterator<'a> {
+        Iterator::with_range(self, options, None, KeyRange::new(&range, self.compare_fn()))
+    }
+
+    fn prefix_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> Iterator<'a> {
+        Iterator::with_range(self, options, None, KeyRange::prefix(prefix, self.compare_fn()))
+    }
+
+    fn prefix_keys_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> KeyIterator<'a> {
+        KeyIterator::with_range(self, options, None, KeyRange::prefix(prefix, self.compare_fn()))
+    }
+
+    fn prefix_value_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> ValueIterator<'a> {
+        ValueIterator::with_range(self, options, None, KeyRange::prefix(prefix, self.compare_fn()))
+    }
+
+    fn cursor(&'a self, options: &ReadOptions) -> RawCursor<'a> {
+        Iterator::new(self, options, None).into_cursor()
+    }
+}
+
+// leveldb iterators may be used from any thread, just not concurrently
+unsafe impl<'a> Send for Iterator<'a> {}
+unsafe impl<'a> Send for RevIterator<'a> {}
+unsafe impl<'a> Send for RawCursor<'a> {}
+
+/// A trait for handles that keep the database alive, to create iterators
+/// that do not borrow from them.
+pub trait OwnedIterable {
+    /// Return an Iterator iterating over (Key,Value) pairs
+    fn owned_iter(&self, options: &ReadOptions) -> OwnedIterator;
+    /// Returns an Iterator iterating over Keys only.
+    fn owned_keys_iter(&self, options: &ReadOptions) -> KeyIterator<'static>;
+    /// Returns an Iterator iterating over Values only.
+    fn owned_value_iter(&self, options: &ReadOptions) -> ValueIterator<'static>;
+    /// Returns an Iterator iterating over the (Key,Value) pairs within `range`.
+    fn owned_range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &ReadOptions, range: R) -> OwnedIterator;
+    /// Returns an Iterator iterating over the (Key,Value) pairs whose keys start with `prefix`.
+    fn owned_prefix_iter(&self, options: &ReadOptions, prefix: &[u8]) -> OwnedIterator;
+}
+
+impl OwnedIterable for Arc<Database> {
+    fn owned_iter(&self, options: &ReadOptions) -> OwnedIterator {
+        Iterator::create(self, options, None, KeyRange::all(), Some(self.clone()))
+    }
+
+    fn owned_keys_iter(&self, options: &ReadOptions) -> KeyIterator<'static> {
+        KeyIterator { inner: self.owned_iter(options) }
+    }
+
+    fn owned_value_iter(&self, options: &ReadOptions) -> ValueIterator<'static> {
+        ValueIterator { inner: self.owned_iter(options) }
+    }
+
+    fn owned_range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &ReadOptions, range: R) -> OwnedIterator {
+        let range = KeyRange::new(&range, self.compare_fn());
+        Iterator::create(self, options, None, range, Some(self.clone()))
+    }
+
+    fn owned_prefix_iter(&self, options: &ReadOptions, prefix: &[u8]) -> OwnedIterator {
+        Iterator::create(self, options, None, KeyRange::prefix(prefix, self.compare_fn()), Some(self.clone()))
+    }
+}
+
+impl OwnedIterable for ArcSnapshot {
+    fn owned_iter(&self, options: &ReadOptions) -> OwnedIterator {
+        Iterator::create(self.database(), options, Some(self.raw_ptr()), KeyRange::all(), Some(self.owner()))
+    }
+
+    fn owned_keys_iter(&self, options: &ReadOptions) -> KeyIterator<'static> {
+        KeyIterator { inner: self.owned_iter(options) }
+    }
+
+    fn owned_value_iter(&self, options: &ReadOptions) -> ValueIterator<'static> {
+        ValueIterator { inner: self.owned_iter(options) }
+    }
+
+    fn owned_range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &ReadOptions, range: R) -> OwnedIterator {
+        let range = KeyRange::new(&range, self.database().compare_fn());
+        Iterator::create(self.database(), options, Some(self.raw_ptr()), range, Some(self.owner()))
+    }
+
+    fn owned_prefix_iter(&self, options: &ReadOptions, prefix: &[u8]) -> OwnedIterator {
+        let range = KeyRange::prefix(prefix, self.database().compare_fn());
+        Iterator::create(self.database(), options, Some(self.raw_ptr()), range, Some(self.owner()))
+    }
 }
 
 pub trait LevelDBIterator<'a> {
@@ -148,6 +501,20 @@ pub trait LevelDBIterator<'a> {
         }
     }
 
+    /// Return the error leveldb encountered while iterating, if any.
+    ///
+    /// leveldb ends iteration when it hits an error, so check the status
+    /// once the iterator is exhausted to tell errors apart from the end of the data.
+    fn status(&self) -> Result<(), Error> {
+        unsafe { raw_status(self.raw_iterator()) }
+    }
+
+    /// Turn the iterator into one yielding `Result`s, that ends with an
+    /// error if iteration stopped because of one.
+    fn fallible(self) -> FallibleIterator<Self> where Self: Sized {
+        FallibleIterator { inner: self, done: false }
+    }
+
     fn seek(&self, key: &[u8]) {
         unsafe {
             leveldb_iter_seek(self.raw_iterator(), key.as_ptr() as *mut c_char, key.len() as size_t);
@@ -157,29 +524,68 @@ pub trait LevelDBIterator<'a> {
 
 impl<'a> Iterator<'a> {
     pub fn new(database: &'a Database, options: &ReadOptions, snapshot: Option<&'a Snapshot>) -> Iterator<'a> {
+        Iterator::with_range(database, options, snapshot, KeyRange::all())
+    }
+
+    pub(crate) fn with_range(database: &'a Database,
+                             options: &ReadOptions,
+                             snapshot: Option<&'a Snapshot>,
+                             range: KeyRange) -> Iterator<'a> {
+        Iterator::create(database, options, snapshot.map(|s| s.raw_ptr()), range, None)
+    }
+
+    /// Iterate over `range` at `snapshot`, which must outlive the iterator.
+    pub(crate) fn at_snapshot(database: &'a Database,
+                              options: &ReadOptions,
+                              snapshot: *mut leveldb_snapshot_t,
+                              range: KeyRange) -> Iterator<'a> {
+        Iterator::create(database, options, Some(snapshot), range, None)
+    }
+
+    fn create(database: &Database,
+              options: &ReadOptions,
+              snapshot: Option<*mut leveldb_snapshot_t>,
+              range: KeyRange,
+              owner: Option<Arc<dyn Send + Sync>>) -> Iterator<'a> {
         unsafe {
             let c_read_options = c_readoptions(options);
 
             if let Some(snapshot) = snapshot {
-                leveldb_readoptions_set_snapshot(c_read_options, snapshot.raw_ptr());
+                leveldb_readoptions_set_snapshot(c_read_options, snapshot);
             }
 
             let ptr = leveldb_create_iterator(database.database.ptr, c_read_options);
 
             leveldb_readoptions_destroy(c_read_options);
-            leveldb_iter_seek_to_first(ptr);
+            range.seek_to_first(ptr);
 
             Iterator {
                 iter: RawIterator { ptr },
                 database: PhantomData,
+                range,
+                owner,
             }
         }
     }
 
+    /// Turn the iterator into a cursor at the same position, keeping its range.
+    pub fn into_cursor(self) -> RawCursor<'a> {
+        RawCursor {
+            iter: self.iter,
+            database: self.database,
+            range: self.range,
+            owner: self.owner,
+        }
+    }
+
     /// return the last element of the iterator
     pub fn last(&self) -> Option<(Vec<u8>, Vec<u8>)> {
         self.seek_to_last();
-        Some((self.key(), self.value()))
+        if self.valid() {
+            Some((self.key(), self.value()))
+        } else {
+            None
+        }
     }
 }
 
@@ -197,8 +603,24 @@ impl<'a> LevelDBIterator<'a> for Iterator<'a> {
         RevIterator {
             database: self.database,
             iter: self.iter,
+            range: self.range,
+            owner: self.owner,
+        }
+    }
+
+    fn valid(&self) -> bool {
+        unsafe {
+            leveldb_iter_valid(self.iter.ptr) != 0 && self.range.contains(raw_key(self.iter.ptr))
         }
     }
+
+    fn seek_to_first(&self) {
+        unsafe { self.range.seek_to_first(self.iter.ptr); }
+    }
+
+    fn seek_to_last(&self) {
+        unsafe { self.range.seek_to_last(self.iter.ptr); }
+    }
 }
 
 impl<'a> LevelDBIterator<'a> for RevIterator<'a> {
@@ -210,11 +632,13 @@ impl<'a> LevelDBIterator<'a> for RevIterator<'a> {
     }
 
     fn reverse(self) -> Self::RevIter {
-        self.seek_to_last();
+        self.seek_to_first();
 
         Iterator {
             database: self.database,
             iter: self.iter,
+            range: self.range,
+            owner: self.owner,
         }
     }
 
@@ -222,6 +646,98 @@ impl<'a> LevelDBIterator<'a> for RevIterator<'a> {
     unsafe fn advance_raw(&mut self) {
         leveldb_iter_prev(self.raw_iterator());
     }
+
+    fn valid(&self) -> bool {
+        unsafe {
+            leveldb_iter_valid(self.iter.ptr) != 0 && self.range.contains(raw_key(self.iter.ptr))
+        }
+    }
+
+    fn seek_to_first(&self) {
+        unsafe { self.range.seek_to_first(self.iter.ptr); }
+    }
+
+    fn seek_to_last(&self) {
+        unsafe { self.range.seek_to_last(self.iter.ptr); }
+    }
+}
+
+impl<'a> RawCursor<'a> {
+    /// Whether the cursor is positioned at an entry within its range.
+    pub fn valid(&self) -> bool {
+        unsafe {
+            leveldb_iter_valid(self.iter.ptr) != 0 && self.range.contains(raw_key(self.iter.ptr))
+        }
+    }
+
+    /// Move to the first entry of the range.
+    pub fn seek_to_first(&mut self) {
+        unsafe { self.range.seek_to_first(self.iter.ptr); }
+    }
+
+    /// Move to the last entry of the range.
+    pub fn seek_to_last(&mut self) {
+        unsafe { self.range.seek_to_last(self.iter.ptr); }
+    }
+
+    /// Move to the first entry with a key at or past `key`.
+    pub fn seek(&mut self, key: &[u8]) {
+        unsafe { raw_seek(self.iter.ptr, key); }
+    }
+
+    /// Move to the next entry. Does nothing if the cursor is not valid.
+    pub fn advance(&mut self) {
+        if self.valid() {
+            unsafe { leveldb_iter_next(self.iter.ptr); }
+        }
+    }
+
+    /// Move to the previous entry. Does nothing if the cursor is not valid.
+    pub fn retreat(&mut self) {
+        if self.valid() {
+            unsafe { leveldb_iter_prev(self.iter.ptr); }
+        }
+    }
+
+    /// The key of the current entry, or `None` if the cursor is not valid.
+    pub fn key_ref(&self) -> Option<&[u8]> {
+        if self.valid() {
+            Some(unsafe { raw_key(self.iter.ptr) })
+        } else {
+            None
+        }
+    }
+
+    /// The value of the current entry, or `None` if the cursor is not valid.
+    pub fn value_ref(&self) -> Option<&[u8]> {
+        if self.valid() {
+            Some(unsafe { raw_value(self.iter.ptr) })
+        } else {
+            None
+        }
+    }
+
+    /// The key and value of the current entry, or `None` if the cursor is not valid.
+    pub fn entry_ref(&self) -> Option<(&[u8], &[u8])> {
+        if self.valid() {
+            Some(unsafe { (raw_key(self.iter.ptr), raw_value(self.iter.ptr)) })
+        } else {
+            None
+        }
+    }
+
+    /// Return the error leveldb encountered while iterating, if any.
+    pub fn status(&self) -> Result<(), Error> {
+        unsafe { raw_status(self.iter.ptr) }
+    }
+
+    /// Call `f` with every entry from the current position to the end of the range.
+    pub fn for_each<F: FnMut(&[u8], &[u8])>(mut self, mut f: F) {
+        while let Some((key, value)) = self.entry_ref() {
+            f(key, value);
+            self.advance();
+        }
+    }
 }
 
 impl<'a> KeyIterator<'a> {
@@ -229,10 +745,21 @@ impl<'a> KeyIterator<'a> {
         KeyIterator { inner: Iterator::new(database, options, snapshot) }
     }
 
+    pub(crate) fn with_range(database: &'a Database,
+                             options: &ReadOptions,
+                             snapshot: Option<&'a Snapshot>,
+                             range: KeyRange) -> KeyIterator<'a> {
+        KeyIterator { inner: Iterator::with_range(database, options, snapshot, range) }
+    }
+
     /// return the last element of the iterator
     pub fn last(self) -> Option<Vec<u8>> {
         self.seek_to_last();
-        Some(self.key())
+        if self.valid() {
+            Some(self.key())
+        } else {
+            None
+        }
     }
 }
 
@@ -241,10 +768,21 @@ impl<'a> ValueIterator<'a> {
         ValueIterator { inner: Iterator::new(database, options, snapshot) }
     }
 
+    pub(crate) fn with_range(database: &'a Database,
+                             options: &ReadOptions,
+                             snapshot: Option<&'a Snapshot>,
+                             range: KeyRange) -> ValueIterator<'a> {
+        ValueIterator { inner: Iterator::with_range(database, options, snapshot, range) }
+    }
+
     /// return the last element of the iterator
     pub fn last(self) -> Option<Vec<u8>> {
         self.seek_to_last();
-        Some(self.value())
+        if self.valid() {
+            Some(self.value())
+        } else {
+            None
+        }
     }
 }
 
@@ -267,6 +805,21 @@ macro_rules! impl_leveldb_iterator {
             fn reverse(self) -> Self::RevIter {
                 Self::RevIter { inner: self.inner.reverse() }
             }
+
+            #[inline]
+            fn valid(&self) -> bool {
+                self.inner.valid()
+            }
+
+            #[inline]
+            fn seek_to_first(&self) {
+                self.inner.seek_to_first();
+            }
+
+            #[inline]
+            fn seek_to_last(&self) {
+                self.inner.seek_to_last();
+            }
         }
     };
 }
@@ -300,4 +853,22 @@ impl_iterator!(RevIterator<'a>, (Vec<u8>,Vec<u8>), entry);
 impl_iterator!(KeyIterator<'a>, Vec<u8>, key);
 impl_iterator!(RevKeyIterator<'a>, Vec<u8>, key);
 impl_iterator!(ValueIterator<'a>, Vec<u8>, value);
-impl_iterator!(RevValueIterator<'a>, Vec<u8>, key);
+impl_iterator!(RevValueIterator<'a>, Vec<u8>, value);
+
+impl<'a, I: LevelDBIterator<'a> + iter::Iterator> iter::Iterator for FallibleIterator<I> {
+    type Item = Result<I::Item, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.inner.next() {
+            Some(item) => Some(Ok(item)),
+            None => {
+                self.done = true;
+                self.inner.status().err().map(Err)
+            }
+        }
+    }
+}
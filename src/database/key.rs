@@ -1,4 +1,5 @@
 use super::error::Error;
+use std::cell::RefCell;
 
 pub trait IntoLevelDBKey {
     fn as_u8_slice_for_write(&self, f: &dyn Fn(&[u8]) -> Result<(), Error>) -> Result<(), Error>;
@@ -70,3 +71,15 @@ impl_into_level_db_key_for_integer!(u16);
 impl_into_level_db_key_for_integer!(u32);
 impl_into_level_db_key_for_integer!(u64);
 impl_into_level_db_key_for_integer!(u128);
+
+/// Copy the bytes of a key into an owned buffer.
+pub(crate) fn key_to_vec(key: &dyn IntoLevelDBKey) -> Vec<u8> {
+    let bytes = RefCell::new(Vec::new());
+    let _ = key.as_u8_slice_for_write(&|k| {
+        bytes.borrow_mut().extend_from_slice(k);
+
+        Ok(())
+    });
+
+    bytes.into_inner()
+}
@@ -0,0 +1,269 @@
+//! Logical keyspaces
+//!
+//! leveldb has no column families. A keyspace emulates one by prefixing
+//! every key with the id of the keyspace, so several independent sets of
+//! keys can share a database. Writes to different keyspaces can be combined
+//! atomically in one `WriteBatch`.
+//!
+//! Keyspace ids are assigned on first use and persisted in a registry.
+//! The registry and all keyspace data live below `KEYSPACE_PREFIX`, keys
+//! written directly to the database must not start with it.
+//!
+//! Keyspaces rely on keys sharing a prefix being adjacent, so they must
+//! not be used with a custom comparator that breaks bytewise prefixes.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::IntoLevelDBKey;
+use super::batch::WriteBatch;
+use super::iterator::{Iterable, Iterator, KeyIterator, ValueIterator, LevelDBIterator, KeyRange};
+use std::iter;
+use std::ops::RangeBounds;
+
+/// The prefix reserved for keyspace data and the keyspace registry.
+pub const KEYSPACE_PREFIX: &[u8] = b"\x00__keyspace__\x00";
+
+// the registry is stored as the keyspace with id 0, mapping names to ids
+const REGISTRY_ID: u32 = 0;
+
+fn keyspace_prefix(id: u32) -> Vec<u8> {
+    let mut prefix = KEYSPACE_PREFIX.to_vec();
+    prefix.extend_from_slice(&id.to_be_bytes());
+    prefix
+}
+
+fn registry_key(name: &str) -> Vec<u8> {
+    let mut key = keyspace_prefix(REGISTRY_ID);
+    key.extend_from_slice(name.as_bytes());
+    key
+}
+
+fn decode_id(value: &[u8]) -> Result<u32, Error> {
+    if value.len() != 4 {
+        return Err(Error::new(format!("corrupt keyspace registry entry of length {}", value.len())));
+    }
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(value);
+
+    Ok(u32::from_be_bytes(bytes))
+}
+
+impl Database {
+    /// Return the keyspace `name`, registering it if it does not exist yet.
+    pub fn keyspace(&self, name: &str) -> Result<Keyspace<'_>, Error> {
+        let mut keyspaces = self.keyspaces.lock().unwrap();
+
+        if let Some(id) = keyspaces.get(name) {
+            return Ok(Keyspace::new(self, name, *id));
+        }
+
+        let read_opts = ReadOptions::new();
+        let id = match self.get_u8(&read_opts, &registry_key(name))? {
+            Some(value) => decode_id(&value)?,
+            None => {
+                let mut last_id = REGISTRY_ID;
+                for (_, value) in self.prefix_iter(&read_opts, &keyspace_prefix(REGISTRY_ID)) {
+                    last_id = last_id.max(decode_id(&value)?);
+                }
+                let id = last_id.checked_add(1)
+                    .ok_or_else(|| Error::new("no keyspace ids left".to_string()))?;
+
+                let mut write_opts = WriteOptions::new();
+                write_opts.sync = true;
+                self.put_u8(&write_opts, &registry_key(name), &id.to_be_bytes())?;
+                id
+            }
+        };
+        keyspaces.insert(name.to_string(), id);
+
+        Ok(Keyspace::new(self, name, id))
+    }
+
+    /// The names of all registered keyspaces, in bytewise order.
+    pub fn keyspace_names(&self) -> Result<Vec<String>, Error> {
+        let prefix = keyspace_prefix(REGISTRY_ID);
+
+        self.prefix_keys_iter(&ReadOptions::new(), &prefix)
+            .map(|key| String::from_utf8(key[prefix.len()..].to_vec())
+                .map_err(|e| Error::new(format!("corrupt keyspace name: {}", e))))
+            .collect()
+    }
+}
+
+/// A handle to a logical keyspace of a database
+///
+/// All keys passed to and returned from a keyspace are relative to it.
+pub struct Keyspace<'a> {
+    database: &'a Database,
+    name: String,
+    id: u32,
+    prefix: Vec<u8>,
+}
+
+impl<'a> Keyspace<'a> {
+    fn new(database: &'a Database, name: &str, id: u32) -> Keyspace<'a> {
+        Keyspace { database, name: name.to_string(), id, prefix: keyspace_prefix(id) }
+    }
+
+    /// The name of the keyspace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The id the keyspace is stored under.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The prefix of all keys of the keyspace in the database.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// The key `key` of the keyspace is stored under in the database.
+    pub fn database_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut bytes = self.prefix.clone();
+        bytes.extend_from_slice(key);
+        bytes
+    }
+
+    pub fn put(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, value: &[u8]) -> Result<(), Error> {
+        key.as_u8_slice_for_write(&|k| {
+            self.put_u8(options, k, value)
+        })
+    }
+
+    pub fn put_u8(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.database.put_u8(options, &self.database_key(key), value)
+    }
+
+    pub fn get(&self, options: &ReadOptions, key: &dyn IntoLevelDBKey) -> Result<Option<Vec<u8>>, Error> {
+        key.as_u8_slice_for_get(&|k| {
+            self.get_u8(options, k)
+        })
+    }
+
+    pub fn get_u8(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.database.get_u8(options, &self.database_key(key))
+    }
+
+    pub fn delete(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey) -> Result<(), Error> {
+        key.as_u8_slice_for_write(&|k| {
+            self.delete_u8(options, k)
+        })
+    }
+
+    pub fn delete_u8(&self, options: &WriteOptions, key: &[u8]) -> Result<(), Error> {
+        self.database.delete_u8(options, &self.database_key(key))
+    }
+
+    /// Return an Iterator iterating over the (Key,Value) pairs of the keyspace.
+    pub fn iter(&self, options: &ReadOptions) -> KeyspaceIterator<Iterator<'a>> {
+        self.wrap(self.database.prefix_iter(options, &self.prefix))
+    }
+
+    /// Returns an Iterator iterating over the Keys of the keyspace.
+    pub fn keys_iter(&self, options: &ReadOptions) -> KeyspaceKeyIterator<KeyIterator<'a>> {
+        KeyspaceKeyIterator {
+            inner: self.database.prefix_keys_iter(options, &self.prefix),
+            prefix_len: self.prefix.len(),
+        }
+    }
+
+    /// Returns an Iterator iterating over the Values of the keyspace.
+    pub fn value_iter(&self, options: &ReadOptions) -> ValueIterator<'a> {
+        self.database.prefix_value_iter(options, &self.prefix)
+    }
+
+    /// Returns an Iterator iterating over the (Key,Value) pairs within `range`.
+    pub fn range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &ReadOptions, range: R) -> KeyspaceIterator<Iterator<'a>> {
+        let range = KeyRange::within_prefix(&self.prefix, &range);
+
+        self.wrap(Iterator::with_range(self.database, options, None, range))
+    }
+
+    /// Returns an Iterator iterating over the (Key,Value) pairs whose keys start with `prefix`.
+    pub fn prefix_iter(&self, options: &ReadOptions, prefix: &[u8]) -> KeyspaceIterator<Iterator<'a>> {
+        self.wrap(self.database.prefix_iter(options, &self.database_key(prefix)))
+    }
+
+    fn wrap(&self, inner: Iterator<'a>) -> KeyspaceIterator<Iterator<'a>> {
+        KeyspaceIterator { inner, prefix_len: self.prefix.len() }
+    }
+}
+
+impl WriteBatch {
+    /// Batch a put operation on `keyspace`
+    pub fn put_in(&self, keyspace: &Keyspace, key: &dyn IntoLevelDBKey, value: &[u8]) {
+        let _ = key.as_u8_slice_for_write(&|k| {
+            self.put_u8_in(keyspace, k, value);
+
+            Ok(())
+        });
+    }
+
+    pub fn put_u8_in(&self, keyspace: &Keyspace, key: &[u8], value: &[u8]) {
+        self.put_u8(&keyspace.database_key(key), value);
+    }
+
+    /// Batch a delete operation on `keyspace`
+    pub fn delete_in(&self, keyspace: &Keyspace, key: &dyn IntoLevelDBKey) {
+        let _ = key.as_u8_slice_for_write(&|k| {
+            self.delete_u8_in(keyspace, k);
+
+            Ok(())
+        });
+    }
+
+    pub fn delete_u8_in(&self, keyspace: &Keyspace, key: &[u8]) {
+        self.delete_u8(&keyspace.database_key(key));
+    }
+}
+
+/// An iterator over the (Key,Value) pairs of a keyspace, with keys
+/// relative to the keyspace.
+pub struct KeyspaceIterator<I> {
+    inner: I,
+    prefix_len: usize,
+}
+
+/// An iterator over the Keys of a keyspace, relative to the keyspace.
+pub struct KeyspaceKeyIterator<I> {
+    inner: I,
+    prefix_len: usize,
+}
+
+macro_rules! impl_keyspace_iterator {
+    ($T:ident) => {
+        impl<I> $T<I> {
+            /// Iterate over the keyspace in the opposite direction.
+            pub fn reverse<'a>(self) -> $T<I::RevIter> where I: LevelDBIterator<'a> {
+                $T { inner: self.inner.reverse(), prefix_len: self.prefix_len }
+            }
+
+            /// Return the error leveldb encountered while iterating, if any.
+            pub fn status<'a>(&self) -> Result<(), Error> where I: LevelDBIterator<'a> {
+                self.inner.status()
+            }
+        }
+    };
+}
+
+impl_keyspace_iterator!(KeyspaceIterator);
+impl_keyspace_iterator!(KeyspaceKeyIterator);
+
+impl<I: iter::Iterator<Item = (Vec<u8>, Vec<u8>)>> iter::Iterator for KeyspaceIterator<I> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, value)| (key[self.prefix_len..].to_vec(), value))
+    }
+}
+
+impl<I: iter::Iterator<Item = Vec<u8>>> iter::Iterator for KeyspaceKeyIterator<I> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|key| key[self.prefix_len..].to_vec())
+    }
+}
@@ -0,0 +1,137 @@
+//! Forwarding of leveldb's info log
+//!
+//! leveldb logs its diagnostics, such as compactions, stalled writes and
+//! recoveries, to a `LOG` file in the database directory. A `Logger` set as
+//! `Options::info_log` receives them instead.
+use leveldb_sys::leveldb_logger_t;
+use libc::{size_t, c_void, c_char};
+use std::slice;
+use std::sync::Arc;
+
+extern "C" {
+    // from shim/leveldb_shim.cc, a `leveldb::Logger` calling `log` with each message
+    fn rs_leveldb_logger_create(
+        state: *mut c_void,
+        destructor: extern "C" fn(*mut c_void),
+        log: extern "C" fn(*mut c_void, *const c_char, size_t)
+    ) -> *mut leveldb_logger_t;
+    fn rs_leveldb_logger_destroy(logger: *mut leveldb_logger_t);
+}
+
+type Callback = Box<dyn Fn(&str) + Send + Sync>;
+
+extern "C" fn log_message(state: *mut c_void, message: *const c_char, length: size_t) {
+    let callback = unsafe { &*(state as *const Callback) };
+    let bytes = unsafe { slice::from_raw_parts(message as *const u8, length) };
+    callback(String::from_utf8_lossy(bytes).trim_end());
+}
+
+extern "C" fn destroy_callback(state: *mut c_void) {
+    let _callback: Box<Callback> = unsafe { Box::from_raw(state as *mut Callback) };
+}
+
+/// The severity of a message in the info log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    /// Failed compactions and manifest writes.
+    Error,
+    /// Stalled writes, and data dropped during recovery or repair.
+    Warn,
+    /// Compactions, recoveries and repairs.
+    Info,
+    /// Details of compactions, such as the files written and deleted.
+    Debug,
+}
+
+impl LogLevel {
+    /// Guess the severity of a message logged by leveldb.
+    ///
+    /// leveldb logs all messages alike, so the level is derived from the
+    /// wording of the messages of leveldb 1.22.
+    pub fn of(message: &str) -> LogLevel {
+        let message = message.trim_start().to_ascii_lowercase();
+        let warnings = ["ignoring", "dropping", "dropped", "unparsable", "waiting", "archiving", "repaired"];
+        let details = ["delete type=", "generated table", "compacted to:", "expanding@", "compact:"];
+
+        // errors leveldb ignores, e.g. during recovery, are only warnings
+        if (message.contains("error") && !message.contains("ignoring")) || message.starts_with("manifest write") {
+            LogLevel::Error
+        } else if warnings.iter().any(|word| message.contains(word)) {
+            LogLevel::Warn
+        } else if details.iter().any(|prefix| message.starts_with(prefix)) {
+            LogLevel::Debug
+        } else {
+            LogLevel::Info
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+struct RawLogger {
+    ptr: *mut leveldb_logger_t,
+}
+
+unsafe impl Sync for RawLogger {}
+unsafe impl Send for RawLogger {}
+
+impl Drop for RawLogger {
+    fn drop(&mut self) {
+        unsafe {
+            rs_leveldb_logger_destroy(self.ptr);
+        }
+    }
+}
+
+/// Receives the messages leveldb writes to its info log
+///
+/// Databases opened with a logger keep a handle to it, so it stays
+/// alive for as long as any database uses it.
+#[derive(Clone, Debug)]
+pub struct Logger {
+    raw: Arc<RawLogger>,
+}
+
+impl Logger {
+    /// Create a logger calling `callback` with every message.
+    ///
+    /// leveldb calls it from its background compaction thread as well.
+    pub fn new<F: Fn(&str) + Send + Sync + 'static>(callback: F) -> Logger {
+        let state: Box<Callback> = Box::new(Box::new(callback));
+        let ptr = unsafe {
+            rs_leveldb_logger_create(Box::into_raw(state) as *mut c_void, destroy_callback, log_message)
+        };
+        Logger { raw: Arc::new(RawLogger { ptr }) }
+    }
+
+    /// Create a logger emitting every message through the `log` crate,
+    /// with target `leveldb` and the level given by `LogLevel::of`.
+    pub fn log() -> Logger {
+        Logger::new(|message| {
+            let level = match LogLevel::of(message) {
+                LogLevel::Error => log::Level::Error,
+                LogLevel::Warn => log::Level::Warn,
+                LogLevel::Info => log::Level::Info,
+                LogLevel::Debug => log::Level::Debug,
+            };
+            log::log!(target: "leveldb", level, "{}", message);
+        })
+    }
+
+    /// Create a logger emitting every message as a `tracing` event,
+    /// with target `leveldb` and the level given by `LogLevel::of`.
+    #[cfg(feature = "tracing")]
+    pub fn tracing() -> Logger {
+        Logger::new(|message| match LogLevel::of(message) {
+            LogLevel::Error => tracing::error!(target: "leveldb", "{}", message),
+            LogLevel::Warn => tracing::warn!(target: "leveldb", "{}", message),
+            LogLevel::Info => tracing::info!(target: "leveldb", "{}", message),
+            LogLevel::Debug => tracing::debug!(target: "leveldb", "{}", message),
+        })
+    }
+
+    #[allow(missing_docs)]
+    pub fn raw_ptr(&self) -> *mut leveldb_logger_t {
+        self.raw.ptr
+    }
+}
@@ -1,7 +1,17 @@
-use super::options::{Options, c_options};
+use super::options::{Options, ReadOptions, WriteOptions, c_options};
 use super::error::Error;
+use super::db::Database;
+use super::comparator::Comparator;
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, LevelDBIterator};
+use super::snapshots::Snapshots;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::convert::TryFrom;
 use std::ffi::CString;
-use std::path::Path;
+use std::ffi::OsString;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::ptr;
 use libc::c_char;
 
@@ -43,3 +53,357 @@ pub fn repair(name: &Path, options: &Options) -> Result<(), Error> {
     }
 }
 
+
+// the start of binary dumps, followed by the format version
+const DUMP_MAGIC: &[u8] = b"LDBDUMP\0";
+const DUMP_VERSION: u32 = 1;
+// the tags of the records of binary dumps
+const TAG_END: u8 = 0;
+const TAG_ENTRY: u8 = 1;
+const CSV_HEADER: &str = "key,value";
+
+/// The formats of database dumps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A versioned binary format of length-prefixed keys and values,
+    /// ending with the entry count and a CRC32 of the entries.
+    Binary,
+    /// One JSON object per line, with the base64 encoded `key` and `value`.
+    ///
+    /// Loading dumps in this format requires the `json` feature.
+    JsonLines,
+    /// A `key,value` header, then one line of base64 encoded key and value per entry.
+    Csv,
+}
+
+/// Options for `load_with`
+#[derive(Debug)]
+pub struct LoadOptions {
+    /// The format of the dump, detected from its first bytes if `None`.
+    pub format: Option<DumpFormat>,
+    /// The maximum number of entries written in one `WriteBatch`.
+    pub batch_size: usize,
+    /// The options the database is opened with.
+    ///
+    /// default: `Options::new()` with `create_if_missing` set
+    pub options: Options,
+}
+
+impl LoadOptions {
+    pub fn new() -> LoadOptions {
+        let mut options = Options::new();
+        options.create_if_missing = true;
+        LoadOptions {
+            format: None,
+            batch_size: 10_000,
+            options,
+        }
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions::new()
+    }
+}
+
+fn io_error(e: io::Error) -> Error {
+    Error::new(format!("dump i/o error: {}", e))
+}
+
+fn format_error(message: &str) -> Error {
+    Error::new(format!("invalid dump: {}", message))
+}
+
+/// Write all entries of `database` to `writer` in `format`.
+///
+/// The entries are read from a snapshot, so the dump is consistent.
+/// Returns the number of entries written.
+pub fn dump<W: Write>(database: &Database, writer: W, format: DumpFormat) -> Result<u64, Error> {
+    let mut writer = BufWriter::new(writer);
+    let snapshot = database.snapshot();
+    let entries = snapshot.iter(&ReadOptions::new()).fallible();
+
+    let mut count: u64 = 0;
+    match format {
+        DumpFormat::Binary => {
+            writer.write_all(DUMP_MAGIC).map_err(io_error)?;
+            writer.write_all(&DUMP_VERSION.to_be_bytes()).map_err(io_error)?;
+            let mut hasher = crc32fast::Hasher::new();
+            for entry in entries {
+                let (key, value) = entry?;
+                let mut record = vec![TAG_ENTRY];
+                for field in [&key, &value] {
+                    let len = u32::try_from(field.len())
+                        .map_err(|_| Error::new("entry too large for a binary dump".to_string()))?;
+                    record.extend_from_slice(&len.to_be_bytes());
+                    record.extend_from_slice(field);
+                }
+                hasher.update(&record);
+                writer.write_all(&record).map_err(io_error)?;
+                count += 1;
+            }
+            writer.write_all(&[TAG_END]).map_err(io_error)?;
+            writer.write_all(&count.to_be_bytes()).map_err(io_error)?;
+            writer.write_all(&hasher.finalize().to_be_bytes()).map_err(io_error)?;
+        }
+        DumpFormat::JsonLines => {
+            for entry in entries {
+                let (key, value) = entry?;
+                // base64 needs no escaping in JSON strings
+                writeln!(writer, "{{\"key\":\"{}\",\"value\":\"{}\"}}", BASE64.encode(key), BASE64.encode(value))
+                    .map_err(io_error)?;
+                count += 1;
+            }
+        }
+        DumpFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER).map_err(io_error)?;
+            for entry in entries {
+                let (key, value) = entry?;
+                writeln!(writer, "{},{}", BASE64.encode(key), BASE64.encode(value)).map_err(io_error)?;
+                count += 1;
+            }
+        }
+    }
+    writer.flush().map_err(io_error)?;
+
+    Ok(count)
+}
+
+/// Load a dump from `reader` into the database at `path`, creating it if missing.
+///
+/// See `load_with`.
+pub fn load<R: Read>(path: &Path, reader: R) -> Result<u64, Error> {
+    load_with(path, reader, &LoadOptions::new())
+}
+
+/// Load a dump from `reader` into the database at `path`.
+///
+/// The database is opened with `load_options.options`. The dump is first
+/// read into a staging database next to `path`, named after it with a
+/// `.loading` suffix, and the checksum of a binary dump is verified. Only a
+/// completely read and valid dump is then copied into the database, in
+/// batches, overwriting existing keys. An invalid dump leaves the database
+/// unchanged. Returns the number of entries loaded.
+///
+/// Dumps keep no comparator information. A database created with a custom
+/// comparator must be loaded with `load_with_comparator`.
+pub fn load_with<R: Read>(path: &Path, reader: R, load_options: &LoadOptions) -> Result<u64, Error> {
+    let database = Database::open(path, &load_options.options)?;
+    load_into(&database, path, reader, load_options)
+}
+
+/// Load a dump from `reader` into the database at `path`, which uses `comparator`.
+///
+/// See `load_with`. The entries are ordered by `comparator`, whatever
+/// the order of the database they were dumped from.
+pub fn load_with_comparator<R: Read, C: Comparator>(path: &Path,
+                                                    reader: R,
+                                                    load_options: &LoadOptions,
+                                                    comparator: C)
+                                                    -> Result<u64, Error> {
+    let database = Database::open_with_comparator(path, &load_options.options, comparator)?;
+    load_into(&database, path, reader, load_options)
+}
+
+fn load_into<R: Read>(database: &Database, path: &Path, reader: R, load_options: &LoadOptions) -> Result<u64, Error> {
+    // the staging database lives in the same environment as the database
+    let mut staging_options = Options::new();
+    staging_options.create_if_missing = true;
+    staging_options.env = load_options.options.env.clone();
+
+    let staging_path = staging_path(path);
+    // left over by an interrupted load
+    destroy(&staging_path, &staging_options)?;
+    let loaded = Database::open(&staging_path, &staging_options).and_then(|staging| {
+        read_dump(&staging, reader, load_options)?;
+        copy_entries(&staging, database, load_options.batch_size)
+    });
+    destroy(&staging_path, &staging_options)?;
+
+    loaded
+}
+
+fn staging_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map_or_else(OsString::new, |name| name.to_os_string());
+    name.push(".loading");
+    path.with_file_name(name)
+}
+
+fn copy_entries(from: &Database, to: &Database, batch_size: usize) -> Result<u64, Error> {
+    let mut loader = Loader::new(to, batch_size);
+    for entry in from.iter(&ReadOptions::new()).fallible() {
+        let (key, value) = entry?;
+        loader.put(&key, &value)?;
+    }
+    loader.finish()
+}
+
+fn read_dump<R: Read>(database: &Database, reader: R, load_options: &LoadOptions) -> Result<u64, Error> {
+    let mut reader = BufReader::new(reader);
+    let format = match load_options.format {
+        Some(format) => format,
+        None => detect_format(&mut reader)?,
+    };
+
+    let mut loader = Loader::new(database, load_options.batch_size);
+    match format {
+        DumpFormat::Binary => load_binary(&mut reader, &mut loader)?,
+        DumpFormat::JsonLines => load_json_lines(reader, &mut loader)?,
+        DumpFormat::Csv => {
+            let mut lines = reader.lines();
+            match lines.next() {
+                Some(Ok(header)) if header.trim_end() == CSV_HEADER => {}
+                _ => return Err(format_error("missing csv header")),
+            }
+            for line in lines {
+                let line = line.map_err(io_error)?;
+                let line = line.trim_end();
+                if line.is_empty() {
+                    continue;
+                }
+                let (key, value) = line.split_once(',').ok_or_else(|| format_error("expected two csv fields"))?;
+                let decode = |text: &str| BASE64.decode(text).map_err(|e| format_error(&e.to_string()));
+                loader.put(&decode(key)?, &decode(value)?)?;
+            }
+        }
+    }
+
+    loader.finish()
+}
+
+fn detect_format<R: Read>(reader: &mut BufReader<R>) -> Result<DumpFormat, Error> {
+    let start = reader.fill_buf().map_err(io_error)?;
+    if start.starts_with(DUMP_MAGIC) {
+        return Ok(DumpFormat::Binary);
+    }
+    if start.starts_with(b"{") {
+        return Ok(DumpFormat::JsonLines);
+    }
+
+    Ok(DumpFormat::Csv)
+}
+
+#[cfg(feature = "json")]
+fn load_json_lines<R: BufRead>(reader: R, loader: &mut Loader) -> Result<(), Error> {
+    for line in reader.lines() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object: serde_json::Value = serde_json::from_str(&line)
+            .map_err(|e| format_error(&e.to_string()))?;
+        let field = |name: &str| -> Result<Vec<u8>, Error> {
+            let text = object[name].as_str().ok_or_else(|| format_error(&format!("missing {}", name)))?;
+            BASE64.decode(text).map_err(|e| format_error(&e.to_string()))
+        };
+        loader.put(&field("key")?, &field("value")?)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "json"))]
+fn load_json_lines<R: BufRead>(_reader: R, _loader: &mut Loader) -> Result<(), Error> {
+    Err(Error::new("loading JSON Lines dumps requires the json feature".to_string()))
+}
+
+// writes loaded entries in batches
+struct Loader<'a> {
+    database: &'a Database,
+    batch: WriteBatch,
+    batched: usize,
+    batch_size: usize,
+    loaded: u64,
+}
+
+impl<'a> Loader<'a> {
+    fn new(database: &'a Database, batch_size: usize) -> Loader<'a> {
+        Loader {
+            database,
+            batch: WriteBatch::new(),
+            batched: 0,
+            batch_size: batch_size.max(1),
+            loaded: 0,
+        }
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.batch.put_u8(key, value);
+        self.batched += 1;
+        self.loaded += 1;
+        if self.batched == self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.database.write(&WriteOptions::new(), &self.batch)?;
+        self.batch.clear();
+        self.batched = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<u64, Error> {
+        if self.batched > 0 {
+            self.flush()?;
+        }
+        Ok(self.loaded)
+    }
+}
+
+fn load_binary<R: Read>(reader: &mut R, loader: &mut Loader) -> Result<(), Error> {
+    let mut read = |len: u32| -> Result<Vec<u8>, Error> {
+        // the length is untrusted, so the buffer only grows as data arrives
+        let mut buf = Vec::new();
+        reader.by_ref().take(len as u64).read_to_end(&mut buf).map_err(io_error)?;
+        if buf.len() != len as usize {
+            return Err(format_error("truncated"));
+        }
+        Ok(buf)
+    };
+    let read_u32 = |bytes: Vec<u8>| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    if read(DUMP_MAGIC.len() as u32)? != DUMP_MAGIC {
+        return Err(format_error("not a binary dump"));
+    }
+    let version = read_u32(read(4)?);
+    if version != DUMP_VERSION {
+        return Err(format_error(&format!("unsupported version {}", version)));
+    }
+
+    let mut hasher = crc32fast::Hasher::new();
+    loop {
+        match read(1)?[0] {
+            TAG_ENTRY => {
+                hasher.update(&[TAG_ENTRY]);
+                let mut fields = Vec::with_capacity(2);
+                for _ in 0..2 {
+                    let len = read(4)?;
+                    hasher.update(&len);
+                    let field = read(read_u32(len))?;
+                    hasher.update(&field);
+                    fields.push(field);
+                }
+                loader.put(&fields[0], &fields[1])?;
+            }
+            TAG_END => break,
+            tag => return Err(format_error(&format!("unknown record tag {}", tag))),
+        }
+    }
+
+    let mut count = [0u8; 8];
+    count.copy_from_slice(&read(8)?);
+    let checksum = read_u32(read(4)?);
+    if u64::from_be_bytes(count) != loader.loaded {
+        return Err(format_error("entry count mismatch"));
+    }
+    if checksum != hasher.finalize() {
+        return Err(format_error("checksum mismatch"));
+    }
+    if read(1).is_ok() {
+        return Err(format_error("data after the end of the dump"));
+    }
+
+    Ok(())
+}
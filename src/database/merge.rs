@@ -0,0 +1,302 @@
+//! Merge operators
+//!
+//! leveldb has no merge operator, this module emulates one. `Database::merge`
+//! stores an operand as a separate delta record, without reading the
+//! current value. Reads fold the operands onto the stored value with the
+//! `MergeOperator` set in `Options::merge_operator`. `Database::collapse`
+//! folds them eagerly and writes the result back, to keep reads cheap.
+//!
+//! Operands are stored below `MERGE_PREFIX`, keys written directly to the
+//! database must not start with it. Iterators return the stored values and
+//! operand records as they are, collapse a range before scanning it.
+//!
+//! When a merge operator is set, every write discards the pending operands
+//! of the keys it puts or deletes, including writes through a `WriteBatch`.
+//! Merges do not take the key locks of the atomic operations, so a merge racing with
+//! `update` or `compare_and_swap` on the same key may be lost. Other writes
+//! are serialized with `collapse`, which therefore never overwrites a value
+//! written after it read the key.
+//!
+//! Operands are ordered by the system clock and a per-handle counter,
+//! so they keep their order across restarts unless the clock goes back.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::{IntoLevelDBKey, key_to_vec};
+use super::batch::{WriteBatch, WriteBatchIterator, write_raw};
+use super::changes::ChangeEvent;
+use super::iterator::{Iterable, Iterator, KeyRange, LevelDBIterator};
+use super::snapshots::{Snapshots, get_u8_at};
+use super::encoding::{self, OrderedDecode};
+use leveldb_sys::leveldb_snapshot_t;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The prefix reserved for merge operands.
+pub const MERGE_PREFIX: &[u8] = b"\x00__merge__\x00";
+
+/// Folds merge operands into a value.
+pub trait MergeOperator: Send + Sync {
+    /// Apply `operands`, oldest first, to the `existing` value of `key`,
+    /// which is `None` if the key has no stored value.
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Vec<u8>;
+}
+
+/// Treats values as big-endian `u64` counters and operands as increments.
+///
+/// Values and operands of the wrong length count as 0, overflows wrap.
+pub struct AddU64;
+
+impl MergeOperator for AddU64 {
+    fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Vec<u8> {
+        let to_u64 = |bytes: &[u8]| {
+            let mut value = [0u8; 8];
+            if bytes.len() == value.len() {
+                value.copy_from_slice(bytes);
+            }
+            u64::from_be_bytes(value)
+        };
+        let sum = operands.iter()
+            .fold(existing.map_or(0, to_u64), |sum, operand| sum.wrapping_add(to_u64(operand)));
+
+        sum.to_be_bytes().to_vec()
+    }
+}
+
+/// Appends operands to the value.
+pub struct Append;
+
+impl MergeOperator for Append {
+    fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Vec<u8> {
+        let mut value = existing.unwrap_or_default().to_vec();
+        for operand in operands {
+            value.extend_from_slice(operand);
+        }
+        value
+    }
+}
+
+// operand records, as pairs of their database key and the operand
+type Operands = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// The merge operator of a database and the state to order its operands.
+pub(crate) struct Merge {
+    operator: Arc<dyn MergeOperator>,
+    sequence: AtomicU64,
+    // serializes collapses with the writes that discard operands
+    writes: Mutex<()>,
+}
+
+impl Merge {
+    pub(crate) fn new(operator: Arc<dyn MergeOperator>) -> Merge {
+        Merge { operator, sequence: AtomicU64::new(0), writes: Mutex::new(()) }
+    }
+
+    /// Hold off collapses and other writes until the guard is dropped.
+    pub(crate) fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        self.writes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn fold(&self, key: &[u8], existing: Option<&[u8]>, operands: &Operands) -> Vec<u8> {
+        let operands: Vec<&[u8]> = operands.iter().map(|(_, operand)| operand.as_slice()).collect();
+        self.operator.merge(key, existing, &operands)
+    }
+
+    // a sequence number larger than all previous ones of this handle,
+    // and usually larger than those of previous handles
+    fn next_sequence(&self) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        let mut last = self.sequence.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self.sequence.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return next,
+                Err(current) => last = current,
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Merge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Merge").field("sequence", &self.sequence).finish()
+    }
+}
+
+// all operands of `key` start with this prefix; the escaped key
+// keeps operands of different keys apart and in the order of their keys
+fn operand_prefix(key: &[u8]) -> Vec<u8> {
+    let mut prefix = MERGE_PREFIX.to_vec();
+    prefix.extend_from_slice(&encoding::encode(key));
+    prefix
+}
+
+// the key the operand record `operand_key` belongs to
+fn operand_owner(operand_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut data = &operand_key[MERGE_PREFIX.len()..];
+    Vec::decode_ordered(&mut data)
+}
+
+// the stored value of `key` at `snapshot` and its pending operand records
+fn read_with_operands(database: &Database,
+                      snapshot: *mut leveldb_snapshot_t,
+                      options: &ReadOptions,
+                      key: &[u8])
+                      -> Result<(Option<Vec<u8>>, Operands), Error> {
+    let existing = get_u8_at(database, snapshot, options, key)?;
+    let operands = Iterator::at_snapshot(database, options, snapshot, KeyRange::prefix(&operand_prefix(key), database.compare_fn()))
+        .fallible()
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((existing, operands))
+}
+
+/// Read `key` from `snapshot` of `database`, folding its pending operands.
+pub(crate) fn get_merged(database: &Database,
+                         snapshot: *mut leveldb_snapshot_t,
+                         merge: &Merge,
+                         options: &ReadOptions,
+                         key: &[u8])
+                         -> Result<Option<Vec<u8>>, Error> {
+    let (existing, operands) = read_with_operands(database, snapshot, options, key)?;
+    if operands.is_empty() {
+        return Ok(existing);
+    }
+
+    Ok(Some(merge.fold(key, existing.as_deref(), &operands)))
+}
+
+impl Database {
+    fn merge_state(&self) -> Result<&Merge, Error> {
+        self.merge.as_ref().ok_or_else(|| Error::new("the database has no merge operator".to_string()))
+    }
+
+    /// Record `operand` to be merged into the value of `key`.
+    ///
+    /// Fails if the database was opened without a merge operator.
+    pub fn merge(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, operand: &[u8]) -> Result<(), Error> {
+        self.merge_u8(options, &key_to_vec(key), operand)
+    }
+
+    pub fn merge_u8(&self, options: &WriteOptions, key: &[u8], operand: &[u8]) -> Result<(), Error> {
+        let merge = self.merge_state()?;
+        let mut operand_key = operand_prefix(key);
+        operand_key.extend_from_slice(&merge.next_sequence().to_be_bytes());
+
+        let batch = WriteBatch::new();
+        batch.put_u8(&operand_key, operand);
+        self.write_keeping_operands(options, &batch)
+    }
+
+    /// `batch`, preceded by deletes of the pending operands of the keys it writes.
+    pub(crate) fn discarding_operands(&self, batch: &WriteBatch) -> WriteBatch {
+        let mut written = WrittenKeys(Vec::new());
+        batch.for_each(&mut written);
+        let mut keys = written.0;
+        keys.sort();
+        keys.dedup();
+
+        // operands are ordered like their keys, one iterator visits them all
+        let discarding = WriteBatch::new();
+        let mut operand_keys = self.prefix_keys_iter(&ReadOptions::new(), MERGE_PREFIX);
+        for key in keys.iter().filter(|key| !key.starts_with(MERGE_PREFIX)) {
+            let prefix = operand_prefix(key);
+            operand_keys.seek(&prefix);
+            for operand_key in operand_keys.by_ref() {
+                if !operand_key.starts_with(&prefix) {
+                    break;
+                }
+                discarding.delete_u8(&operand_key);
+            }
+        }
+        discarding.append(batch);
+
+        discarding
+    }
+
+    /// Fold the pending operands of all keys within `range` and store the results.
+    ///
+    /// Operands merged while the range is collapsed are kept for the next
+    /// collapse. Returns the number of keys that were collapsed.
+    pub fn collapse<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &WriteOptions, range: R) -> Result<u64, Error> {
+        let merge = self.merge_state()?;
+        let to_vec = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(key_to_vec(key)),
+            Bound::Excluded(key) => Bound::Excluded(key_to_vec(key)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let range = (to_vec(range.start_bound()), to_vec(range.end_bound()));
+
+        // operands are ordered like their keys, so start at the first key of the range
+        let operand_keys = self.prefix_keys_iter(&ReadOptions::new(), MERGE_PREFIX);
+        if let Bound::Included(ref start) | Bound::Excluded(ref start) = range.0 {
+            operand_keys.seek(&operand_prefix(start));
+        }
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        for operand_key in operand_keys {
+            let key = operand_owner(&operand_key)?;
+            if past_end(&range.1, &key) {
+                break;
+            }
+            if keys.last() != Some(&key) && range.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        let read_options = ReadOptions::new();
+        let mut collapsed = 0;
+        for key in &keys {
+            let _guard = self.key_locks.lock(key);
+            let written = RefCell::new(None);
+            self.publish(|| {
+                // no put or delete of the key can land between the read and the write
+                let _writes = merge.lock_writes();
+                let snapshot = self.snapshot();
+                let (existing, operands) = read_with_operands(self, snapshot.raw_ptr(), &read_options, key)?;
+                if operands.is_empty() {
+                    return Ok(());
+                }
+
+                let batch = WriteBatch::new();
+                batch.put_u8(key, &merge.fold(key, existing.as_deref(), &operands));
+                for (operand_key, _) in &operands {
+                    batch.delete_u8(operand_key);
+                }
+                // operands merged since the snapshot are kept
+                write_raw(self, options, &batch)?;
+                *written.borrow_mut() = Some(batch);
+                Ok(())
+            }, || written.borrow().as_ref().map_or_else(Vec::new, ChangeEvent::from_batch))?;
+            if written.into_inner().is_some() {
+                collapsed += 1;
+            }
+        }
+
+        Ok(collapsed)
+    }
+}
+
+// the keys written by a batch
+struct WrittenKeys(Vec<Vec<u8>>);
+
+impl WriteBatchIterator for WrittenKeys {
+    fn put_u8(&mut self, key: &[u8], _value: &[u8]) {
+        self.0.push(key.to_vec());
+    }
+
+    fn deleted_u8(&mut self, key: &[u8]) {
+        self.0.push(key.to_vec());
+    }
+}
+
+fn past_end(end: &Bound<Vec<u8>>, key: &[u8]) -> bool {
+    match end {
+        Bound::Included(end) => key > end.as_slice(),
+        Bound::Excluded(end) => key >= end.as_slice(),
+        Bound::Unbounded => false,
+    }
+}
@@ -11,6 +11,21 @@ pub mod compaction;
 pub mod comparator;
 pub mod key;
 pub mod util;
+pub mod properties;
+pub mod filter_policy;
+pub mod env;
+pub mod logger;
+pub mod typed;
+pub mod encoding;
+pub mod keyspace;
+pub mod transaction;
+pub mod atomic;
+pub mod ttl;
+pub mod merge;
+pub mod range_delete;
+pub mod changes;
+pub mod index;
+pub mod backup;
 
 
 pub use db::Database;
\ No newline at end of file
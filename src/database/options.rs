@@ -3,6 +3,11 @@ use leveldb_sys::*;
 use libc::size_t;
 
 use super::cache::Cache;
+use super::filter_policy::Filter;
+use super::env::Env;
+use super::logger::Logger;
+use super::merge::MergeOperator;
+use std::sync::Arc;
 
 /// Options to consider when opening a new or pre-existing database.
 ///
@@ -49,6 +54,22 @@ pub struct Options {
     ///
     /// default: None
     pub cache: Option<Cache>,
+    /// A filter policy to reduce disk reads for missing keys.
+    ///
+    /// default: None
+    pub filter_policy: Option<Filter>,
+    /// The environment leveldb uses to access files.
+    ///
+    /// default: None
+    pub env: Option<Env>,
+    /// Receives leveldb's info log instead of the `LOG` file.
+    ///
+    /// default: None
+    pub info_log: Option<Logger>,
+    /// The operator to fold the operands written with `Database::merge`.
+    ///
+    /// default: None
+    pub merge_operator: Option<Arc<dyn MergeOperator>>,
 }
 
 impl std::fmt::Debug for Options {
@@ -78,6 +99,10 @@ impl Options {
             block_restart_interval: None,
             compression: Compression::No,
             cache: None,
+            filter_policy: None,
+            env: None,
+            info_log: None,
+            merge_operator: None,
         }
     }
 }
@@ -150,6 +175,15 @@ pub unsafe fn c_options(options: &Options,
     if let Some(ref cache) = options.cache {
         leveldb_options_set_cache(c_options, cache.raw_ptr());
     }
+    if let Some(ref filter_policy) = options.filter_policy {
+        leveldb_options_set_filter_policy(c_options, filter_policy.raw_ptr());
+    }
+    if let Some(ref env) = options.env {
+        leveldb_options_set_env(c_options, env.raw_ptr());
+    }
+    if let Some(ref info_log) = options.info_log {
+        leveldb_options_set_info_log(c_options, info_log.raw_ptr());
+    }
     c_options
 }
 
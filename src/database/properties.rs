@@ -0,0 +1,216 @@
+//! leveldb properties
+//!
+//! leveldb exposes some of its internal state through named properties.
+//! This module allows to read them, either as the raw string leveldb reports
+//! or parsed into structs.
+use leveldb_sys::{leveldb_property_value, leveldb_approximate_sizes, leveldb_free};
+use libc::{c_char, c_int, c_void, size_t};
+use std::ffi::{CStr, CString};
+use super::Database;
+use super::key::{IntoLevelDBKey, key_to_vec};
+
+/// The number of levels leveldb organizes its tables in.
+pub const NUM_LEVELS: usize = 7;
+
+/// Compaction statistics of a single level, as reported by `leveldb.stats`.
+///
+/// leveldb rounds sizes to whole megabytes and times to whole seconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelStats {
+    /// The level these statistics belong to.
+    pub level: usize,
+    /// The number of table files in the level.
+    pub files: u64,
+    /// The total size of the level in megabytes.
+    pub size_mb: f64,
+    /// The time spent compacting into this level, in seconds.
+    pub time_sec: f64,
+    /// The data read by compactions into this level, in megabytes.
+    pub read_mb: f64,
+    /// The data written by compactions into this level, in megabytes.
+    pub write_mb: f64,
+}
+
+/// The parsed output of the `leveldb.stats` property.
+///
+/// Levels without files and without compaction activity are omitted by leveldb.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    /// Statistics for every reported level.
+    pub levels: Vec<LevelStats>,
+}
+
+/// A table file, as reported by `leveldb.sstables`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SSTable {
+    /// The level the table lives in.
+    pub level: usize,
+    /// The file number of the table.
+    pub number: u64,
+    /// The size of the table file in bytes.
+    pub size: u64,
+    /// The smallest internal key of the table, as formatted by leveldb.
+    pub smallest: String,
+    /// The largest internal key of the table, as formatted by leveldb.
+    pub largest: String,
+}
+
+/// Access to leveldb's internal properties.
+pub trait Properties {
+    /// Return the value of the property `name`, or `None` if the property
+    /// is unknown to leveldb.
+    fn property(&self, name: &str) -> Option<String>;
+
+    /// Return the approximate file system space used by keys in each of the
+    /// `[start, limit)` ranges.
+    ///
+    /// Data that was recently written may not be reflected until it is
+    /// compacted out of the memtable.
+    fn approximate_sizes(&self, ranges: &[(&dyn IntoLevelDBKey, &dyn IntoLevelDBKey)]) -> Vec<u64> {
+        let ranges: Vec<(Vec<u8>, Vec<u8>)> = ranges.iter()
+            .map(|(start, limit)| (key_to_vec(*start), key_to_vec(*limit)))
+            .collect();
+        let ranges: Vec<(&[u8], &[u8])> = ranges.iter()
+            .map(|(start, limit)| (start.as_slice(), limit.as_slice()))
+            .collect();
+
+        self.approximate_sizes_u8(&ranges)
+    }
+
+    /// Same as `approximate_sizes`, for ranges given as raw bytes.
+    fn approximate_sizes_u8(&self, ranges: &[(&[u8], &[u8])]) -> Vec<u64>;
+
+    /// The number of table files at `level` (`leveldb.num-files-at-level<N>`).
+    fn num_files_at_level(&self, level: usize) -> Option<u64> {
+        self.property(&format!("leveldb.num-files-at-level{}", level))
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    /// The approximate number of bytes of memory in use
+    /// (`leveldb.approximate-memory-usage`).
+    fn approximate_memory_usage(&self) -> Option<u64> {
+        self.property("leveldb.approximate-memory-usage")
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    /// Per-level compaction statistics (`leveldb.stats`).
+    fn stats(&self) -> Option<Stats> {
+        self.property("leveldb.stats").and_then(|value| parse_stats(&value))
+    }
+
+    /// All table files making up the database (`leveldb.sstables`).
+    fn sstables(&self) -> Option<Vec<SSTable>> {
+        self.property("leveldb.sstables").and_then(|value| parse_sstables(&value))
+    }
+}
+
+impl Properties for Database {
+    fn property(&self, name: &str) -> Option<String> {
+        let c_name = CString::new(name).ok()?;
+
+        unsafe {
+            let value = leveldb_property_value(self.database.ptr, c_name.as_ptr() as *const c_char);
+
+            if value.is_null() {
+                None
+            } else {
+                let result = CStr::from_ptr(value).to_string_lossy().into_owned();
+                leveldb_free(value as *mut c_void);
+                Some(result)
+            }
+        }
+    }
+
+    fn approximate_sizes_u8(&self, ranges: &[(&[u8], &[u8])]) -> Vec<u64> {
+        let start_keys: Vec<*const c_char> = ranges.iter().map(|r| r.0.as_ptr() as *const c_char).collect();
+        let start_lens: Vec<size_t> = ranges.iter().map(|r| r.0.len() as size_t).collect();
+        let limit_keys: Vec<*const c_char> = ranges.iter().map(|r| r.1.as_ptr() as *const c_char).collect();
+        let limit_lens: Vec<size_t> = ranges.iter().map(|r| r.1.len() as size_t).collect();
+        let mut sizes = vec![0u64; ranges.len()];
+
+        unsafe {
+            leveldb_approximate_sizes(self.database.ptr,
+                                      ranges.len() as c_int,
+                                      start_keys.as_ptr(),
+                                      start_lens.as_ptr(),
+                                      limit_keys.as_ptr(),
+                                      limit_lens.as_ptr(),
+                                      sizes.as_mut_ptr());
+        }
+
+        sizes
+    }
+}
+
+fn parse_stats(value: &str) -> Option<Stats> {
+    let mut levels = Vec::new();
+
+    // the first three lines are the table header
+    for line in value.lines().skip(3) {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.is_empty() {
+            continue;
+        }
+        if columns.len() != 6 {
+            return None;
+        }
+
+        levels.push(LevelStats {
+            level: columns[0].parse().ok()?,
+            files: columns[1].parse().ok()?,
+            size_mb: columns[2].parse().ok()?,
+            time_sec: columns[3].parse().ok()?,
+            read_mb: columns[4].parse().ok()?,
+            write_mb: columns[5].parse().ok()?,
+        });
+    }
+
+    Some(Stats { levels })
+}
+
+fn parse_sstables(value: &str) -> Option<Vec<SSTable>> {
+    let mut tables = Vec::new();
+    let mut level = None;
+
+    for line in value.lines() {
+        if let Some(header) = line.strip_prefix("--- level ") {
+            level = Some(header.trim_end_matches(" ---").parse().ok()?);
+            continue;
+        }
+        let line = line.trim_start();
+        if line.is_empty() {
+            continue;
+        }
+
+        // e.g. `17:123['a' @ 1 : 1 .. 'd' @ 5 : 1]`
+        let (number, rest) = line.split_at(line.find(':')?);
+        let (size, range) = rest[1..].split_at(rest[1..].find('[')?);
+        let range = range[1..].strip_suffix(']')?;
+        // keys are escaped by leveldb but keep quotes and spaces, so they may
+        // contain " .. " or "' @ ": split where both sides are whole keys
+        let split = range.rmatch_indices(" .. ")
+            .map(|(i, _)| i)
+            .find(|&i| is_internal_key(&range[..i]) && is_internal_key(&range[i + " .. ".len()..]))?;
+
+        tables.push(SSTable {
+            level: level?,
+            number: number.parse().ok()?,
+            size: size.parse().ok()?,
+            smallest: range[..split].to_string(),
+            largest: range[split + " .. ".len()..].to_string(),
+        });
+    }
+
+    Some(tables)
+}
+
+// whether `key` is formatted like `'user key' @ sequence : type`
+fn is_internal_key(key: &str) -> bool {
+    let number = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    let (user_key, rest) = match key.rsplit_once(" @ ") {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let quoted = user_key.len() >= 2 && user_key.starts_with('\'') && user_key.ends_with('\'');
+    quoted && rest.split_once(" : ").is_some_and(|(sequence, kind)| number(sequence) && number(kind))
+}
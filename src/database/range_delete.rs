@@ -0,0 +1,122 @@
+//! Range deletes
+//!
+//! leveldb can only delete single keys. The helpers in this module list the
+//! keys of a range from a snapshot and delete them, either all in one
+//! `WriteBatch` or in batches of bounded size.
+//!
+//! Keys written to the range while it is deleted are not part of the
+//! snapshot, so they survive the delete.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::IntoLevelDBKey;
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, LevelDBIterator};
+use super::snapshots::{Snapshot, Snapshots};
+use super::compaction::Compaction;
+use std::ops::RangeBounds;
+
+/// Options for `Database::delete_range_with`
+#[derive(Clone, Copy, Debug)]
+pub struct DeleteRangeOptions {
+    /// The maximum number of keys deleted in one `WriteBatch`.
+    pub batch_size: usize,
+    /// Compact the range once its keys are deleted, to reclaim their space.
+    pub compact: bool,
+}
+
+impl DeleteRangeOptions {
+    pub fn new() -> DeleteRangeOptions {
+        DeleteRangeOptions {
+            batch_size: 1000,
+            compact: false,
+        }
+    }
+}
+
+impl Default for DeleteRangeOptions {
+    fn default() -> DeleteRangeOptions {
+        DeleteRangeOptions::new()
+    }
+}
+
+impl Database {
+    /// Delete all keys within `range`.
+    ///
+    /// Returns the number of keys deleted. See `delete_range_with`.
+    pub fn delete_range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, options: &WriteOptions, range: R) -> Result<u64, Error> {
+        self.delete_range_with(options, range, &DeleteRangeOptions::new(), |_| {})
+    }
+
+    /// Delete all keys within `range`, in batches of at most
+    /// `range_options.batch_size` keys.
+    ///
+    /// `progress` is called with the total number of keys deleted after
+    /// each batch. The batches are not atomic together: if a write fails,
+    /// the keys of the earlier batches stay deleted. Returns the number of
+    /// keys deleted.
+    pub fn delete_range_with<K, R, F>(&self,
+                                      options: &WriteOptions,
+                                      range: R,
+                                      range_options: &DeleteRangeOptions,
+                                      mut progress: F)
+                                      -> Result<u64, Error>
+        where K: IntoLevelDBKey, R: RangeBounds<K>, F: FnMut(u64)
+    {
+        let batch_size = range_options.batch_size.max(1);
+        let snapshot = self.snapshot();
+
+        let batch = WriteBatch::new();
+        let mut batched = 0;
+        let mut deleted = 0;
+        let mut flush = |batched: &mut usize| -> Result<(), Error> {
+            self.write(options, &batch)?;
+            batch.clear();
+            deleted += *batched as u64;
+            *batched = 0;
+            progress(deleted);
+            Ok(())
+        };
+
+        let mut bounds: Option<(Vec<u8>, Vec<u8>)> = None;
+        for entry in snapshot.range(&ReadOptions::new(), range).fallible() {
+            let key = entry?.0;
+            batch.delete_u8(&key);
+            batched += 1;
+            match bounds {
+                Some((_, ref mut last)) => *last = key,
+                None => bounds = Some((key.clone(), key)),
+            }
+
+            if batched == batch_size {
+                flush(&mut batched)?;
+            }
+        }
+        if batched > 0 {
+            flush(&mut batched)?;
+        }
+
+        if range_options.compact {
+            if let Some((first, last)) = bounds {
+                self.compact(&first, &last);
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+impl WriteBatch {
+    /// Batch a delete of every key within `range` in `snapshot`.
+    ///
+    /// Returns the number of deletes added to the batch.
+    pub fn delete_range<K: IntoLevelDBKey, R: RangeBounds<K>>(&self, snapshot: &Snapshot, range: R) -> Result<u64, Error> {
+        let mut count = 0;
+        for entry in snapshot.range(&ReadOptions::new(), range).fallible() {
+            self.delete_u8(&entry?.0);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
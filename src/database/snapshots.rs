@@ -8,9 +8,12 @@ use super::db::Database;
 use super::error::Error;
 use super::options::{ReadOptions, c_readoptions};
 use super::key::IntoLevelDBKey;
-use super::iterator::{Iterable, Iterator, KeyIterator, ValueIterator};
+use super::iterator::{Iterable, Iterator, KeyIterator, ValueIterator, RawCursor, KeyRange};
 use super::bytes::Bytes;
+use super::merge::get_merged;
 use std::ptr;
+use std::ops::RangeBounds;
+use std::sync::Arc;
 use libc::{c_char, size_t};
 
 #[allow(missing_docs)]
@@ -56,32 +59,37 @@ impl<'a> Snapshot<'a> {
                options: &ReadOptions,
                key: &[u8])
                -> Result<Option<Vec<u8>>, Error> {
+        match self.database.merge {
+            Some(ref merge) => get_merged(self.database, self.raw_ptr(), merge, options, key),
+            None => get_u8_at(self.database, self.raw_ptr(), options, key),
+        }
+    }
+
+    /// fetches several keys from the snapshot
+    ///
+    /// The read options are converted once and shared by all lookups.
+    /// Returns one result per key, in the order of `keys`.
+    pub fn multi_get(&self,
+                     options: &ReadOptions,
+                     keys: &[impl IntoLevelDBKey])
+                     -> Vec<Result<Option<Vec<u8>>, Error>> {
+        if self.database.merge.is_some() {
+            // folding reads the operands of every key separately
+            return keys.iter().map(|key| self.get(options, key)).collect();
+        }
+
         unsafe {
-            let mut error = ptr::null_mut();
-            let mut length: size_t = 0;
             let c_readoptions = c_readoptions(options);
-
-            // add the extra snapshot information to c_readoptions
             leveldb_readoptions_set_snapshot(c_readoptions, self.raw_ptr());
 
-            let result = leveldb_get(self.database.database.ptr,
-                                     c_readoptions,
-                                     key.as_ptr() as *mut c_char,
-                                     key.len() as size_t,
-                                     &mut length,
-                                     &mut error);
+            let results = keys.iter()
+                .map(|key| key.as_u8_slice_for_get(&|k| get_with(self.database, c_readoptions, k)))
+                .collect();
 
             leveldb_readoptions_destroy(c_readoptions);
 
-            if error == ptr::null_mut() {
-                let bytes_opt = Bytes::from_raw(result as *mut u8, length);
-
-                Ok(bytes_opt.map(|val| {val.into()}))
-            } else {
-                Err(Error::new_from_char(error))
-            }
+            results
         }
-
     }
 
     #[inline]
@@ -91,6 +99,49 @@ impl<'a> Snapshot<'a> {
     }
 }
 
+pub(crate) fn get_u8_at(database: &Database,
+                        snapshot: *mut leveldb_snapshot_t,
+                        options: &ReadOptions,
+                        key: &[u8])
+                        -> Result<Option<Vec<u8>>, Error> {
+    unsafe {
+        let c_readoptions = c_readoptions(options);
+
+        // add the extra snapshot information to c_readoptions
+        leveldb_readoptions_set_snapshot(c_readoptions, snapshot);
+
+        let result = get_with(database, c_readoptions, key);
+
+        leveldb_readoptions_destroy(c_readoptions);
+
+        result
+    }
+}
+
+// look up `key` with read options already converted for leveldb
+unsafe fn get_with(database: &Database,
+                   c_readoptions: *mut leveldb_readoptions_t,
+                   key: &[u8])
+                   -> Result<Option<Vec<u8>>, Error> {
+    let mut error = ptr::null_mut();
+    let mut length: size_t = 0;
+
+    let result = leveldb_get(database.database.ptr,
+                             c_readoptions,
+                             key.as_ptr() as *mut c_char,
+                             key.len() as size_t,
+                             &mut length,
+                             &mut error);
+
+    if error.is_null() {
+        let bytes_opt = Bytes::from_raw(result as *mut u8, length);
+
+        Ok(bytes_opt.map(|val| {val.into()}))
+    } else {
+        Err(Error::new_from_char(error))
+    }
+}
+
 /// Structs implementing the Snapshots trait can be
 /// snapshotted.
 pub trait Snapshots {
@@ -101,21 +152,91 @@ pub trait Snapshots {
 
 impl Snapshots for Database {
     fn snapshot(&self) -> Snapshot {
-        let db_str = self.database.ptr;
+        Snapshot {
+            raw: RawSnapshot::new(self),
+            database: self
+        }
+    }
+}
+
+impl RawSnapshot {
+    fn new(database: &Database) -> RawSnapshot {
+        let db_str = database.database.ptr;
         let snap = unsafe {
             leveldb_create_snapshot(db_str)
         };
 
-        let raw = RawSnapshot {
+        RawSnapshot {
             db_ptr: db_str,
             ptr: snap,
-        };
+        }
+    }
+}
 
-        Snapshot {
-            raw,
-            database: self
+struct OwnedSnapshot {
+    // released before the database handle is dropped
+    raw: RawSnapshot,
+    database: Arc<Database>,
+}
+
+unsafe impl Sync for OwnedSnapshot {}
+unsafe impl Send for OwnedSnapshot {}
+
+/// A database snapshot that keeps its database alive
+///
+/// Unlike `Snapshot`, it does not borrow the database. It can be cloned,
+/// stored and sent to other threads. The snapshot is released once the
+/// last clone is dropped.
+#[derive(Clone)]
+pub struct ArcSnapshot {
+    inner: Arc<OwnedSnapshot>,
+}
+
+impl ArcSnapshot {
+    /// Create a snapshot of `database`.
+    pub fn new(database: Arc<Database>) -> ArcSnapshot {
+        let raw = RawSnapshot::new(&database);
+
+        ArcSnapshot { inner: Arc::new(OwnedSnapshot { raw, database }) }
+    }
+
+    /// fetches a key from the database
+    pub fn get(&self,
+               options: &ReadOptions,
+               key: &dyn IntoLevelDBKey)
+               -> Result<Option<Vec<u8>>, Error> {
+        key.as_u8_slice_for_get(& |k| {
+            self.get_u8(options, k)
+        })
+    }
+
+    /// fetches a key given as raw bytes from the database
+    pub fn get_u8(&self,
+                  options: &ReadOptions,
+                  key: &[u8])
+                  -> Result<Option<Vec<u8>>, Error> {
+        let database = &self.inner.database;
+        match database.merge {
+            Some(ref merge) => get_merged(database, self.raw_ptr(), merge, options, key),
+            None => get_u8_at(database, self.raw_ptr(), options, key),
         }
     }
+
+    /// The database this snapshot was taken from.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.inner.database
+    }
+
+    /// A handle keeping the snapshot and its database alive.
+    pub(crate) fn owner(&self) -> Arc<dyn Send + Sync> {
+        self.inner.clone()
+    }
+
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn raw_ptr(&self) -> *mut leveldb_snapshot_t {
+        self.inner.raw.ptr
+    }
 }
 
 impl<'a> Iterable<'a> for Snapshot<'a> {
@@ -130,5 +251,25 @@ impl<'a> Iterable<'a> for Snapshot<'a> {
     fn value_iter(&'a self, options: &ReadOptions)-> ValueIterator<'a> {
         ValueIterator::new(self.database, options, Some(self))
     }
+
+    fn range<K: IntoLevelDBKey, R: RangeBounds<K>>(&'a self, options: &ReadOptions, range: R) -> Iterator<'a> {
+        Iterator::with_range(self.database, options, Some(self), KeyRange::new(&range, self.database.compare_fn()))
+    }
+
+    fn prefix_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> Iterator<'a> {
+        Iterator::with_range(self.database, options, Some(self), KeyRange::prefix(prefix, self.database.compare_fn()))
+    }
+
+    fn prefix_keys_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> KeyIterator<'a> {
+        KeyIterator::with_range(self.database, options, Some(self), KeyRange::prefix(prefix, self.database.compare_fn()))
+    }
+
+    fn prefix_value_iter(&'a self, options: &ReadOptions, prefix: &[u8]) -> ValueIterator<'a> {
+        ValueIterator::with_range(self.database, options, Some(self), KeyRange::prefix(prefix, self.database.compare_fn()))
+    }
+
+    fn cursor(&'a self, options: &ReadOptions) -> RawCursor<'a> {
+        Iterator::new(self.database, options, Some(self)).into_cursor()
+    }
 }
 
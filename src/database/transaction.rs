@@ -0,0 +1,184 @@
+//! Optimistic transactions
+//!
+//! A transaction reads from a snapshot and buffers its writes. On commit,
+//! the writes are applied atomically in one `WriteBatch`, unless a key the
+//! transaction read was changed by another transaction that committed in the
+//! meantime. In that case the commit fails with an error of kind
+//! `ErrorKind::Conflict`, and the transaction can be retried.
+//!
+//! Conflicts are detected with a version table kept by the `Database` handle.
+//! Only commits of transactions are tracked: writes made directly through
+//! the database or a `WriteBatch` are not seen as conflicts.
+use super::Database;
+use super::error::{Error, ErrorKind};
+use super::options::{ReadOptions, WriteOptions};
+use super::key::{IntoLevelDBKey, key_to_vec};
+use super::batch::{Batch, WriteBatch};
+use super::snapshots::{Snapshot, Snapshots};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// keep the versions of at most this many keys while transactions are running
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// The commit versions of keys written by transactions.
+#[derive(Debug, Default)]
+pub(crate) struct VersionTable {
+    // the version of the last commit
+    sequence: u64,
+    // the version of the last commit that wrote each key
+    versions: HashMap<Vec<u8>, u64>,
+    // the number of running transactions, by the version they started at
+    active: BTreeMap<u64, usize>,
+}
+
+impl VersionTable {
+    fn begin(&mut self) -> u64 {
+        *self.active.entry(self.sequence).or_insert(0) += 1;
+        self.sequence
+    }
+
+    fn end(&mut self, start: u64) {
+        if let Some(count) = self.active.get_mut(&start) {
+            *count -= 1;
+            if *count == 0 {
+                self.active.remove(&start);
+            }
+        }
+
+        // versions at or before the start of every running transaction
+        // can never cause a conflict
+        match self.active.keys().next() {
+            None => self.versions.clear(),
+            Some(&oldest) if self.versions.len() > PRUNE_THRESHOLD => {
+                self.versions.retain(|_, version| *version > oldest);
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn changed_since(&self, key: &[u8], start: u64) -> bool {
+        self.versions.get(key).is_some_and(|version| *version > start)
+    }
+}
+
+impl Database {
+    /// Begin an optimistic transaction.
+    pub fn transaction(&self) -> Transaction<'_> {
+        let mut versions = self.versions.lock().unwrap();
+        // take the snapshot under the lock, so no commit falls between
+        // the snapshot and the start version
+        let snapshot = self.snapshot();
+        let start = versions.begin();
+
+        Transaction {
+            database: self,
+            snapshot,
+            start,
+            reads: RefCell::new(HashSet::new()),
+            writes: BTreeMap::new(),
+        }
+    }
+}
+
+/// An optimistic transaction
+///
+/// Reads see the state of the database when the transaction began, plus
+/// the transaction's own writes. Dropping a transaction without committing
+/// discards its writes.
+pub struct Transaction<'a> {
+    database: &'a Database,
+    snapshot: Snapshot<'a>,
+    start: u64,
+    reads: RefCell<HashSet<Vec<u8>>>,
+    // `None` marks a delete
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> Transaction<'a> {
+    /// fetches a key, tracking it for conflicts
+    pub fn get(&self, options: &ReadOptions, key: &dyn IntoLevelDBKey) -> Result<Option<Vec<u8>>, Error> {
+        key.as_u8_slice_for_get(&|k| {
+            self.get_u8(options, k)
+        })
+    }
+
+    /// fetches a key given as raw bytes, tracking it for conflicts
+    pub fn get_u8(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(value) = self.writes.get(key) {
+            return Ok(value.clone());
+        }
+        self.reads.borrow_mut().insert(key.to_vec());
+
+        self.snapshot.get_u8(options, key)
+    }
+
+    /// Buffer a put operation
+    pub fn put(&mut self, key: &dyn IntoLevelDBKey, value: &[u8]) {
+        self.writes.insert(key_to_vec(key), Some(value.to_vec()));
+    }
+
+    pub fn put_u8(&mut self, key: &[u8], value: &[u8]) {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    /// Buffer a delete operation
+    pub fn delete(&mut self, key: &dyn IntoLevelDBKey) {
+        self.writes.insert(key_to_vec(key), None);
+    }
+
+    pub fn delete_u8(&mut self, key: &[u8]) {
+        self.writes.insert(key.to_vec(), None);
+    }
+
+    /// The snapshot the transaction reads from.
+    pub fn snapshot(&self) -> &Snapshot<'a> {
+        &self.snapshot
+    }
+
+    /// Apply the buffered writes atomically.
+    ///
+    /// Fails with an error of kind `ErrorKind::Conflict` if a key read by
+    /// the transaction was written by a transaction that committed after
+    /// this one began. Nothing is written in that case.
+    pub fn commit(self, options: &WriteOptions) -> Result<(), Error> {
+        let mut versions = self.database.versions.lock().unwrap();
+
+        if let Some(key) = self.reads.borrow().iter().find(|key| versions.changed_since(key, self.start)) {
+            return Err(Error::with_kind(ErrorKind::Conflict,
+                                        format!("transaction conflict on key {:?}", key)));
+        }
+        if self.writes.is_empty() {
+            return Ok(());
+        }
+
+        let batch = WriteBatch::new();
+        for (key, value) in &self.writes {
+            match value {
+                Some(value) => batch.put_u8(key, value),
+                None => batch.delete_u8(key),
+            }
+        }
+        self.database.write(options, &batch)?;
+
+        versions.sequence += 1;
+        let sequence = versions.sequence;
+        for key in self.writes.keys() {
+            versions.versions.insert(key.clone(), sequence);
+        }
+
+        Ok(())
+    }
+
+    /// Discard the transaction and its writes.
+    pub fn rollback(self) {}
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        // runs after commit returned, so the lock is free again
+        if let Ok(mut versions) = self.database.versions.lock() {
+            versions.end(self.start);
+        }
+    }
+}
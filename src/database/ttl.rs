@@ -0,0 +1,358 @@
+//! Per-key time-to-live
+//!
+//! `TtlDatabase` stores an expiry time in front of every value. Expired
+//! entries are hidden from `get` and the iterators, but stay in the database
+//! until they are purged, either by `purge_expired` or by a background
+//! `Sweeper`.
+//!
+//! All values of a `TtlDatabase` must be written through it. Iterators skip
+//! entries that do not carry an expiry time, `get` fails on them.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::key::{IntoLevelDBKey, key_to_vec};
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, Iterator, LevelDBIterator};
+use std::iter;
+use std::ops::RangeBounds;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// the expiry time stored for entries without a time-to-live
+const NEVER: u64 = 0;
+const HEADER_LEN: usize = 8;
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+fn encode_value(expires_at: u64, value: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + value.len());
+    bytes.extend_from_slice(&expires_at.to_be_bytes());
+    bytes.extend_from_slice(value);
+    bytes
+}
+
+// split a stored value into its expiry time and the value
+fn decode_value(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    if bytes.len() < HEADER_LEN {
+        return None;
+    }
+    let (header, value) = bytes.split_at(HEADER_LEN);
+    let mut expires_at = [0u8; HEADER_LEN];
+    expires_at.copy_from_slice(header);
+
+    Some((u64::from_be_bytes(expires_at), value))
+}
+
+fn is_expired(expires_at: u64, now: u64) -> bool {
+    expires_at != NEVER && expires_at <= now
+}
+
+/// A database whose entries can expire
+pub struct TtlDatabase {
+    database: Arc<Database>,
+}
+
+impl TtlDatabase {
+    /// Wrap `database`. All of its values must have been written by a `TtlDatabase`.
+    pub fn new(database: Arc<Database>) -> TtlDatabase {
+        TtlDatabase { database }
+    }
+
+    /// The underlying database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+
+    /// Store `value` under `key` without expiry.
+    pub fn put(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, value: &[u8]) -> Result<(), Error> {
+        self.put_u8_expiring_at(options, &key_to_vec(key), value, NEVER)
+    }
+
+    pub fn put_u8(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.put_u8_expiring_at(options, key, value, NEVER)
+    }
+
+    /// Store `value` under `key`, hiding it once `ttl` has passed.
+    pub fn put_with_ttl(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        self.put_u8_with_ttl(options, &key_to_vec(key), value, ttl)
+    }
+
+    pub fn put_u8_with_ttl(&self, options: &WriteOptions, key: &[u8], value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let expires_at = now_millis().saturating_add(ttl.as_millis() as u64).max(1);
+        self.put_u8_expiring_at(options, key, value, expires_at)
+    }
+
+    fn put_u8_expiring_at(&self, options: &WriteOptions, key: &[u8], value: &[u8], expires_at: u64) -> Result<(), Error> {
+        // keeps a sweeper from deleting the new value
+        let _guard = self.database.key_locks.lock(key);
+        self.database.put_u8(options, key, &encode_value(expires_at, value))
+    }
+
+    /// fetches a key, returning `None` if it expired
+    pub fn get(&self, options: &ReadOptions, key: &dyn IntoLevelDBKey) -> Result<Option<Vec<u8>>, Error> {
+        key.as_u8_slice_for_get(&|k| {
+            self.get_u8(options, k)
+        })
+    }
+
+    pub fn get_u8(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.get_with_expiry(options, key)?.map(|(value, _)| value))
+    }
+
+    /// The remaining time-to-live of `key`, `None` if it does not expire
+    /// or is missing.
+    pub fn ttl(&self, options: &ReadOptions, key: &dyn IntoLevelDBKey) -> Result<Option<Duration>, Error> {
+        let expiry = self.get_with_expiry(options, &key_to_vec(key))?
+            .map(|(_, expires_at)| expires_at)
+            .filter(|expires_at| *expires_at != NEVER);
+
+        Ok(expiry.map(|expires_at| Duration::from_millis(expires_at.saturating_sub(now_millis()))))
+    }
+
+    fn get_with_expiry(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<(Vec<u8>, u64)>, Error> {
+        let bytes = match self.database.get_u8(options, key)? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let (expires_at, value) = decode_value(&bytes)
+            .ok_or_else(|| Error::new("value has no expiry time".to_string()))?;
+        if is_expired(expires_at, now_millis()) {
+            return Ok(None);
+        }
+
+        Ok(Some((value.to_vec(), expires_at)))
+    }
+
+    pub fn delete(&self, options: &WriteOptions, key: &dyn IntoLevelDBKey) -> Result<(), Error> {
+        self.database.delete(options, key)
+    }
+
+    pub fn delete_u8(&self, options: &WriteOptions, key: &[u8]) -> Result<(), Error> {
+        self.database.delete_u8(options, key)
+    }
+
+    /// Return an Iterator over the (Key,Value) pairs that have not expired.
+    pub fn iter<'a>(&'a self, options: &ReadOptions) -> TtlIterator<Iterator<'a>> {
+        TtlIterator::new(self.database.iter(options))
+    }
+
+    /// Returns an Iterator over the Keys that have not expired.
+    pub fn keys_iter<'a>(&'a self, options: &ReadOptions) -> TtlKeyIterator<Iterator<'a>> {
+        TtlKeyIterator { inner: TtlIterator::new(self.database.iter(options)) }
+    }
+
+    /// Returns an Iterator over the unexpired (Key,Value) pairs within `range`.
+    pub fn range<'a, K: IntoLevelDBKey, R: RangeBounds<K>>(&'a self, options: &ReadOptions, range: R) -> TtlIterator<Iterator<'a>> {
+        TtlIterator::new(self.database.range(options, range))
+    }
+
+    /// Returns an Iterator over the unexpired (Key,Value) pairs whose keys start with `prefix`.
+    pub fn prefix_iter<'a>(&'a self, options: &ReadOptions, prefix: &[u8]) -> TtlIterator<Iterator<'a>> {
+        TtlIterator::new(self.database.prefix_iter(options, prefix))
+    }
+
+    /// Delete all expired entries, in batches of at most `batch_size` keys.
+    ///
+    /// Returns the number of deleted entries.
+    pub fn purge_expired(&self, batch_size: usize) -> Result<u64, Error> {
+        purge(&self.database, batch_size, Duration::from_millis(0), &|| false)
+    }
+
+    /// Start a thread purging expired entries in the background.
+    ///
+    /// The thread stops when the returned `Sweeper` is dropped.
+    pub fn start_sweeper(&self, options: SweeperOptions) -> Sweeper {
+        Sweeper::start(self.database.clone(), options)
+    }
+}
+
+// delete expired entries in chunks, pausing between chunks,
+// until the database is exhausted or `stopped` returns true
+fn purge(database: &Database, batch_size: usize, pause: Duration, stopped: &dyn Fn() -> bool) -> Result<u64, Error> {
+    let batch_size = batch_size.max(1);
+    let mut iter = database.iter(&ReadOptions::new()).fallible();
+    let mut removed = 0;
+
+    loop {
+        let now = now_millis();
+        let mut expired = Vec::with_capacity(batch_size);
+        for entry in iter.by_ref() {
+            let (key, value) = entry?;
+            if decode_value(&value).is_some_and(|(expires_at, _)| is_expired(expires_at, now)) {
+                expired.push(key);
+                if expired.len() == batch_size {
+                    break;
+                }
+            }
+        }
+        if expired.is_empty() {
+            return Ok(removed);
+        }
+
+        // the entries may have been rewritten since they were read,
+        // so check them again while writers are locked out
+        let guards = database.key_locks.lock_many(&expired);
+        let batch = WriteBatch::new();
+        let read_opts = ReadOptions::new();
+        for key in &expired {
+            if let Some(value) = database.get_u8(&read_opts, key)? {
+                if decode_value(&value).is_some_and(|(expires_at, _)| is_expired(expires_at, now)) {
+                    batch.delete_u8(key);
+                    removed += 1;
+                }
+            }
+        }
+        database.write(&WriteOptions::new(), &batch)?;
+        drop(guards);
+
+        if stopped() {
+            return Ok(removed);
+        }
+        if !pause.is_zero() {
+            thread::sleep(pause);
+        }
+    }
+}
+
+/// An iterator over the unexpired (Key,Value) pairs of a `TtlDatabase`.
+///
+/// Entries are checked against the time the iterator was created.
+pub struct TtlIterator<I> {
+    inner: I,
+    now: u64,
+}
+
+/// An iterator over the unexpired Keys of a `TtlDatabase`.
+pub struct TtlKeyIterator<I> {
+    inner: TtlIterator<I>,
+}
+
+impl<I> TtlIterator<I> {
+    fn new(inner: I) -> TtlIterator<I> {
+        TtlIterator { inner, now: now_millis() }
+    }
+
+    /// Iterate in the opposite direction.
+    pub fn reverse<'a>(self) -> TtlIterator<I::RevIter> where I: LevelDBIterator<'a> {
+        TtlIterator { inner: self.inner.reverse(), now: self.now }
+    }
+
+    /// Return the error leveldb encountered while iterating, if any.
+    pub fn status<'a>(&self) -> Result<(), Error> where I: LevelDBIterator<'a> {
+        self.inner.status()
+    }
+}
+
+impl<I: iter::Iterator<Item = (Vec<u8>, Vec<u8>)>> iter::Iterator for TtlIterator<I> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (key, value) in self.inner.by_ref() {
+            match decode_value(&value) {
+                Some((expires_at, value)) if !is_expired(expires_at, self.now) => {
+                    return Some((key, value.to_vec()));
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+impl<I: iter::Iterator<Item = (Vec<u8>, Vec<u8>)>> iter::Iterator for TtlKeyIterator<I> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+/// Options for a background `Sweeper`
+#[derive(Clone, Copy, Debug)]
+pub struct SweeperOptions {
+    /// The time to wait between two passes over the database.
+    pub interval: Duration,
+    /// The maximum number of keys deleted in one `WriteBatch`.
+    pub batch_size: usize,
+    /// The time to wait after each batch, to limit the load on the database.
+    pub pause: Duration,
+}
+
+impl SweeperOptions {
+    pub fn new() -> SweeperOptions {
+        SweeperOptions {
+            interval: Duration::from_secs(60),
+            batch_size: 1000,
+            pause: Duration::from_millis(10),
+        }
+    }
+}
+
+impl Default for SweeperOptions {
+    fn default() -> SweeperOptions {
+        SweeperOptions::new()
+    }
+}
+
+/// A background thread purging expired entries
+///
+/// The thread is stopped and joined when the sweeper is dropped.
+pub struct Sweeper {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    removed: Arc<AtomicU64>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sweeper {
+    fn start(database: Arc<Database>, options: SweeperOptions) -> Sweeper {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let removed = Arc::new(AtomicU64::new(0));
+        let thread_stop = stop.clone();
+        let thread_removed = removed.clone();
+
+        let handle = thread::spawn(move || {
+            let (lock, condvar) = &*thread_stop;
+            let stopped = || *lock.lock().unwrap();
+
+            loop {
+                // errors are retried on the next pass
+                if let Ok(count) = purge(&database, options.batch_size, options.pause, &stopped) {
+                    thread_removed.fetch_add(count, Ordering::Relaxed);
+                }
+
+                let guard = lock.lock().unwrap();
+                let (guard, _) = condvar.wait_timeout_while(guard, options.interval, |stop| !*stop).unwrap();
+                if *guard {
+                    return;
+                }
+            }
+        });
+
+        Sweeper { stop, removed, handle: Some(handle) }
+    }
+
+    /// The number of entries the sweeper deleted so far.
+    pub fn removed(&self) -> u64 {
+        self.removed.load(Ordering::Relaxed)
+    }
+
+    /// Stop the sweeper and wait for its thread to finish.
+    pub fn stop(self) {}
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*self.stop;
+        *lock.lock().unwrap() = true;
+        condvar.notify_all();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
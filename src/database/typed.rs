@@ -0,0 +1,346 @@
+//! Typed access to a database
+//!
+//! `TypedDatabase` stores keys of type `K` and values of type `V`, converting
+//! them from and to bytes on every access. Keys are encoded by `TypedKey`,
+//! in a way that keeps their order under the default bytewise comparator.
+//! Values are encoded by a `Codec`. Codecs based on serde are available
+//! through the `bincode`, `json` and `cbor` features.
+use super::Database;
+use super::error::Error;
+use super::options::{ReadOptions, WriteOptions};
+use super::batch::{Batch, WriteBatch};
+use super::iterator::{Iterable, LevelDBIterator, Iterator, KeyIterator, FallibleIterator};
+use super::encoding::{self, Ordered, OrderedEncode, OrderedDecode};
+use std::iter;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+/// Converts values of type `T` from and to bytes.
+pub trait Codec<T> {
+    /// Encode `value` into bytes.
+    fn encode(value: &T) -> Result<Vec<u8>, Error>;
+    /// Decode a value previously encoded with `encode`.
+    fn decode(data: &[u8]) -> Result<T, Error>;
+}
+
+/// Types that can be used as keys of a `TypedDatabase`.
+///
+/// The encoding must preserve the order of the keys: if `a < b`, then
+/// `a.encode_key() < b.encode_key()` when compared bytewise.
+pub trait TypedKey: Sized {
+    /// Encode the key into bytes.
+    fn encode_key(&self) -> Vec<u8>;
+    /// Decode a key previously encoded with `encode_key`.
+    fn decode_key(data: &[u8]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_typed_key_for_unsigned {
+    ($T: ty) => {
+        impl TypedKey for $T {
+            fn encode_key(&self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+
+            fn decode_key(data: &[u8]) -> Result<$T, Error> {
+                let mut bytes = [0u8; std::mem::size_of::<$T>()];
+                if data.len() != bytes.len() {
+                    return Err(Error::new(format!("invalid key length {} for {}", data.len(), stringify!($T))));
+                }
+                bytes.copy_from_slice(data);
+
+                Ok(<$T>::from_be_bytes(bytes))
+            }
+        }
+    };
+}
+
+impl_typed_key_for_unsigned!(u8);
+impl_typed_key_for_unsigned!(u16);
+impl_typed_key_for_unsigned!(u32);
+impl_typed_key_for_unsigned!(u64);
+impl_typed_key_for_unsigned!(u128);
+
+// signed integers, floats and tuples use the order-preserving encoding
+macro_rules! impl_typed_key_for_ordered {
+    ($T: ty) => {
+        impl TypedKey for $T {
+            fn encode_key(&self) -> Vec<u8> {
+                encoding::encode(self)
+            }
+
+            fn decode_key(data: &[u8]) -> Result<$T, Error> {
+                encoding::decode(data)
+            }
+        }
+    };
+}
+
+impl_typed_key_for_ordered!(i8);
+impl_typed_key_for_ordered!(i16);
+impl_typed_key_for_ordered!(i32);
+impl_typed_key_for_ordered!(i64);
+impl_typed_key_for_ordered!(i128);
+impl_typed_key_for_ordered!(f32);
+impl_typed_key_for_ordered!(f64);
+
+macro_rules! impl_typed_key_for_tuple {
+    ($($T: ident),+) => {
+        impl<$($T: OrderedEncode + OrderedDecode),+> TypedKey for ($($T,)+) {
+            fn encode_key(&self) -> Vec<u8> {
+                encoding::encode(self)
+            }
+
+            fn decode_key(data: &[u8]) -> Result<($($T,)+), Error> {
+                encoding::decode(data)
+            }
+        }
+    };
+}
+
+impl_typed_key_for_tuple!(A, B);
+impl_typed_key_for_tuple!(A, B, C);
+impl_typed_key_for_tuple!(A, B, C, D);
+
+impl<T: OrderedEncode + OrderedDecode> TypedKey for Ordered<T> {
+    fn encode_key(&self) -> Vec<u8> {
+        encoding::encode(&self.0)
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Ordered<T>, Error> {
+        encoding::decode(data).map(Ordered)
+    }
+}
+
+impl TypedKey for String {
+    fn encode_key(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode_key(data: &[u8]) -> Result<String, Error> {
+        String::from_utf8(data.to_vec()).map_err(|e| Error::new(format!("invalid utf-8 key: {}", e)))
+    }
+}
+
+impl TypedKey for Vec<u8> {
+    fn encode_key(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(data.to_vec())
+    }
+}
+
+/// A codec storing byte vectors as they are.
+pub struct RawCodec;
+
+impl Codec<Vec<u8>> for RawCodec {
+    fn encode(value: &Vec<u8>) -> Result<Vec<u8>, Error> {
+        Ok(value.clone())
+    }
+
+    fn decode(data: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(data.to_vec())
+    }
+}
+
+/// A codec using bincode.
+#[cfg(feature = "bincode")]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Bincode {
+    fn encode(value: &T) -> Result<Vec<u8>, Error> {
+        bincode::serialize(value).map_err(|e| Error::new(format!("bincode: {}", e)))
+    }
+
+    fn decode(data: &[u8]) -> Result<T, Error> {
+        bincode::deserialize(data).map_err(|e| Error::new(format!("bincode: {}", e)))
+    }
+}
+
+/// A codec storing values as JSON.
+#[cfg(feature = "json")]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Json {
+    fn encode(value: &T) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(value).map_err(|e| Error::new(format!("json: {}", e)))
+    }
+
+    fn decode(data: &[u8]) -> Result<T, Error> {
+        serde_json::from_slice(data).map_err(|e| Error::new(format!("json: {}", e)))
+    }
+}
+
+/// A codec storing values as CBOR.
+#[cfg(feature = "cbor")]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Cbor {
+    fn encode(value: &T) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data).map_err(|e| Error::new(format!("cbor: {}", e)))?;
+
+        Ok(data)
+    }
+
+    fn decode(data: &[u8]) -> Result<T, Error> {
+        ciborium::from_reader(data).map_err(|e| Error::new(format!("cbor: {}", e)))
+    }
+}
+
+// the typed wrappers own no K, V or C values
+type Marker<K, V, C> = PhantomData<fn() -> (K, V, C)>;
+
+/// A database storing keys of type `K` and values of type `V`,
+/// encoded by the codec `C`.
+pub struct TypedDatabase<K, V, C> {
+    database: Arc<Database>,
+    marker: Marker<K, V, C>,
+}
+
+impl<K: TypedKey, V, C: Codec<V>> TypedDatabase<K, V, C> {
+    /// Wrap `database`.
+    ///
+    /// All keys in the database are expected to be encoded by `K` and
+    /// all values by `C`.
+    pub fn new(database: Arc<Database>) -> TypedDatabase<K, V, C> {
+        TypedDatabase { database, marker: PhantomData }
+    }
+
+    /// The underlying database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+
+    /// Fetch and decode the value of `key`.
+    pub fn get(&self, options: &ReadOptions, key: &K) -> Result<Option<V>, Error> {
+        match self.database.get_u8(options, &key.encode_key())? {
+            Some(data) => C::decode(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Encode and store `value` under `key`.
+    pub fn put(&self, options: &WriteOptions, key: &K, value: &V) -> Result<(), Error> {
+        self.database.put_u8(options, &key.encode_key(), &C::encode(value)?)
+    }
+
+    /// Delete `key`.
+    pub fn delete(&self, options: &WriteOptions, key: &K) -> Result<(), Error> {
+        self.database.delete_u8(options, &key.encode_key())
+    }
+
+    /// Write a batch of typed operations atomically.
+    pub fn write(&self, options: &WriteOptions, batch: &TypedWriteBatch<K, V, C>) -> Result<(), Error> {
+        self.database.write(options, &batch.batch)
+    }
+
+    /// Return an Iterator iterating over decoded (Key,Value) pairs.
+    pub fn iter<'a>(&'a self, options: &ReadOptions) -> TypedIterator<'a, K, V, C> {
+        TypedIterator::new(self.database.iter(options))
+    }
+
+    /// Returns an Iterator iterating over decoded Keys only.
+    pub fn keys_iter<'a>(&'a self, options: &ReadOptions) -> TypedKeyIterator<'a, K> {
+        TypedKeyIterator { inner: self.database.keys_iter(options).fallible(), marker: PhantomData }
+    }
+
+    /// Returns an Iterator iterating over the decoded (Key,Value) pairs within `range`.
+    pub fn range<'a, R: RangeBounds<K>>(&'a self, options: &ReadOptions, range: R) -> TypedIterator<'a, K, V, C> {
+        let encode = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(key.encode_key()),
+            Bound::Excluded(key) => Bound::Excluded(key.encode_key()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let range = (encode(range.start_bound()), encode(range.end_bound()));
+
+        TypedIterator::new(self.database.range(options, range))
+    }
+}
+
+/// A batch of typed writes, applied atomically by `TypedDatabase::write`.
+pub struct TypedWriteBatch<K, V, C> {
+    batch: WriteBatch,
+    marker: Marker<K, V, C>,
+}
+
+impl<K: TypedKey, V, C: Codec<V>> TypedWriteBatch<K, V, C> {
+    /// Create an empty batch.
+    pub fn new() -> TypedWriteBatch<K, V, C> {
+        TypedWriteBatch { batch: WriteBatch::new(), marker: PhantomData }
+    }
+
+    /// Batch a put operation, failing if `value` cannot be encoded.
+    pub fn put(&self, key: &K, value: &V) -> Result<(), Error> {
+        self.batch.put_u8(&key.encode_key(), &C::encode(value)?);
+
+        Ok(())
+    }
+
+    /// Batch a delete operation
+    pub fn delete(&self, key: &K) {
+        self.batch.delete_u8(&key.encode_key());
+    }
+
+    /// Clear the batch
+    pub fn clear(&self) {
+        self.batch.clear();
+    }
+
+    /// The untyped batch holding the encoded operations.
+    pub fn batch(&self) -> &WriteBatch {
+        &self.batch
+    }
+}
+
+impl<K: TypedKey, V, C: Codec<V>> Default for TypedWriteBatch<K, V, C> {
+    fn default() -> TypedWriteBatch<K, V, C> {
+        TypedWriteBatch::new()
+    }
+}
+
+/// An iterator decoding (Key,Value) pairs.
+///
+/// Yields an error for entries that cannot be decoded and for errors
+/// leveldb reports while iterating.
+pub struct TypedIterator<'a, K, V, C> {
+    inner: FallibleIterator<Iterator<'a>>,
+    marker: Marker<K, V, C>,
+}
+
+impl<'a, K, V, C> TypedIterator<'a, K, V, C> {
+    fn new(inner: Iterator<'a>) -> TypedIterator<'a, K, V, C> {
+        TypedIterator { inner: inner.fallible(), marker: PhantomData }
+    }
+}
+
+impl<'a, K: TypedKey, V, C: Codec<V>> iter::Iterator for TypedIterator<'a, K, V, C> {
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| {
+            let (key, value) = entry?;
+
+            Ok((K::decode_key(&key)?, C::decode(&value)?))
+        })
+    }
+}
+
+/// An iterator decoding Keys.
+pub struct TypedKeyIterator<'a, K> {
+    inner: FallibleIterator<KeyIterator<'a>>,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<'a, K: TypedKey> iter::Iterator for TypedKeyIterator<'a, K> {
+    type Item = Result<K, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|key| K::decode_key(&key?))
+    }
+}
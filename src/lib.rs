@@ -15,6 +15,21 @@ pub use database::compaction;
 pub use database::comparator;
 pub use database::key;
 pub use database::util;
+pub use database::properties;
+pub use database::filter_policy;
+pub use database::env;
+pub use database::logger;
+pub use database::typed;
+pub use database::encoding;
+pub use database::keyspace;
+pub use database::transaction;
+pub use database::atomic;
+pub use database::ttl;
+pub use database::merge;
+pub use database::range_delete;
+pub use database::changes;
+pub use database::index;
+pub use database::backup;
 
 use leveldb_sys::{leveldb_major_version, leveldb_minor_version};
 
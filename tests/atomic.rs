@@ -0,0 +1,99 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::options::{ReadOptions, WriteOptions};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_compare_and_swap() {
+  let tmp = temp_dir("cas");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  assert!(database.compare_and_swap(&write_opts, &"lease", None, Some(b"a")).unwrap());
+  assert!(!database.compare_and_swap(&write_opts, &"lease", None, Some(b"b")).unwrap());
+  assert!(!database.compare_and_swap(&write_opts, &"lease", Some(b"b"), Some(b"c")).unwrap());
+  assert_eq!(database.get(&read_opts, &"lease").unwrap(), Some(b"a".to_vec()));
+
+  assert!(database.compare_and_swap(&write_opts, &"lease", Some(b"a"), None).unwrap());
+  assert_eq!(database.get(&read_opts, &"lease").unwrap(), None);
+}
+
+#[test]
+fn test_update() {
+  let tmp = temp_dir("update");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+  db_put_simple(&database, &"list", b"a");
+
+  let new = database.update(&write_opts, &"list", |old| {
+    let mut value = old.unwrap_or_default().to_vec();
+    value.extend_from_slice(b",b");
+    Some(value)
+  }).unwrap();
+  assert_eq!(new, Some(b"a,b".to_vec()));
+
+  assert_eq!(database.update(&write_opts, &"list", |_| None).unwrap(), None);
+  assert_eq!(database.get(&ReadOptions::new(), &"list").unwrap(), None);
+}
+
+#[test]
+fn test_update_closure_uses_database() {
+  let tmp = temp_dir("update_reentrant");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+
+  // some of these keys share the lock stripe of "total"
+  let new = database.update(&write_opts, &"total", |_| {
+    for i in 0..1000 {
+      database.increment_u64(&write_opts, &format!("part{}", i), 1).unwrap();
+    }
+    Some(b"done".to_vec())
+  }).unwrap();
+  assert_eq!(new, Some(b"done".to_vec()));
+}
+
+#[test]
+fn test_increment() {
+  let tmp = temp_dir("increment");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+
+  assert_eq!(database.increment_u64(&write_opts, &"hits", 5).unwrap(), 5);
+  assert_eq!(database.increment_u64(&write_opts, &"hits", 2).unwrap(), 7);
+  assert!(database.increment_u64(&write_opts, &"hits", u64::MAX).is_err());
+
+  assert_eq!(database.increment_i64(&write_opts, &"balance", -3).unwrap(), -3);
+  assert_eq!(database.increment_i64(&write_opts, &"balance", 10).unwrap(), 7);
+
+  db_put_simple(&database, &"text", b"abc");
+  assert!(database.increment_u64(&write_opts, &"text", 1).is_err());
+}
+
+#[test]
+fn test_concurrent_increments() {
+  let tmp = temp_dir("increment_threads");
+  let database = Arc::new(open_database(tmp.path(), true));
+
+  let handles: Vec<_> = (0..8).map(|_| {
+    let database = database.clone();
+    thread::spawn(move || {
+      for _ in 0..100 {
+        database.increment_u64(&WriteOptions::new(), &"counter", 1).unwrap();
+        database.update(&WriteOptions::new(), &"log", |old| {
+          let mut value = old.unwrap_or_default().to_vec();
+          value.push(b'x');
+          Some(value)
+        }).unwrap();
+      }
+    })
+  }).collect();
+  for handle in handles {
+    handle.join().unwrap();
+  }
+
+  assert_eq!(database.increment_u64(&WriteOptions::new(), &"counter", 0).unwrap(), 800);
+  assert_eq!(database.get(&ReadOptions::new(), &"log").unwrap().unwrap().len(), 800);
+}
@@ -0,0 +1,59 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::backup::{read_manifest, BackupOptions, MANIFEST_FILE};
+use leveldb::iterator::Iterable;
+use leveldb::options::{ReadOptions, WriteOptions};
+
+#[test]
+fn test_backup_and_incremental_backup() {
+  let tmp = temp_dir("backup");
+  let database = open_database(&tmp.path().join("db"), true);
+  let target = tmp.path().join("backup");
+  for i in 0..50 {
+    db_put_simple(&database, &i, b"v1");
+  }
+
+  let mut backup_opts = BackupOptions::new();
+  backup_opts.batch_size = 16;
+  let info = database.backup_to_with(&target, &backup_opts).unwrap();
+  assert_eq!((info.entries, info.written, info.deleted), (50, 50, 0));
+  assert_eq!(read_manifest(&target).unwrap(), (info.entries, info.checksum));
+
+  db_put_simple(&database, &3, b"v2");
+  db_put_simple(&database, &60, b"v1");
+  database.delete(&WriteOptions::new(), &7).unwrap();
+  let incremental = database.backup_to(&target).unwrap();
+  assert_eq!((incremental.entries, incremental.written, incremental.deleted), (50, 2, 1));
+  assert_ne!(incremental.checksum, info.checksum);
+
+  let backup = open_database(&target, false);
+  let backed_up: Vec<(Vec<u8>, Vec<u8>)> = backup.iter(&ReadOptions::new()).collect();
+  let current: Vec<(Vec<u8>, Vec<u8>)> = database.iter(&ReadOptions::new()).collect();
+  assert_eq!(backed_up, current);
+}
+
+#[test]
+fn test_backup_refuses_foreign_directory() {
+  let tmp = temp_dir("backup");
+  let database = open_database(&tmp.path().join("db"), true);
+  let other = tmp.path().join("other");
+  open_database(&other, true);
+
+  assert!(database.backup_to(&other).is_err());
+}
+
+#[test]
+fn test_unsynced_backup_writes_manifest() {
+  let tmp = temp_dir("backup");
+  let database = open_database(&tmp.path().join("db"), true);
+  let target = tmp.path().join("backup");
+  db_put_simple(&database, &1, b"v1");
+
+  let mut backup_opts = BackupOptions::new();
+  assert!(backup_opts.sync);
+  backup_opts.sync = false;
+  let info = database.backup_to_with(&target, &backup_opts).unwrap();
+  assert_eq!(read_manifest(&target).unwrap(), (info.entries, info.checksum));
+  assert!(!target.join(format!("{}.tmp", MANIFEST_FILE)).exists());
+}
@@ -0,0 +1,77 @@
+mod utils;
+
+use utils::{open_database, temp_dir};
+use leveldb::batch::{Batch, WriteBatch};
+use leveldb::changes::{ChangeEvent, ChangeFilter, ChangeKind};
+use leveldb::options::WriteOptions;
+use std::sync::Arc;
+
+#[test]
+fn test_subscribe() {
+  let tmp = temp_dir("changes");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+  let events = database.subscribe(ChangeFilter::All);
+
+  database.put(&write_opts, &"a", b"1").unwrap();
+  let batch = WriteBatch::new();
+  batch.put(&"b", b"2");
+  batch.delete(&"a");
+  database.write(&write_opts, &batch).unwrap();
+  database.delete(&write_opts, &"b").unwrap();
+
+  let event = |kind, key: &[u8], value: Option<&[u8]>| ChangeEvent { kind, key: key.to_vec(), value: value.map(|v| v.to_vec()) };
+  let received: Vec<ChangeEvent> = events.try_iter().collect();
+  assert_eq!(received, vec![
+    event(ChangeKind::Put, b"a", Some(b"1")),
+    event(ChangeKind::Put, b"b", Some(b"2")),
+    event(ChangeKind::Delete, b"a", None),
+    event(ChangeKind::Delete, b"b", None),
+  ]);
+}
+
+#[test]
+fn test_subscribe_filters() {
+  let tmp = temp_dir("changes");
+  let database = open_database(tmp.path(), true);
+  let write_opts = WriteOptions::new();
+  let users = database.subscribe(ChangeFilter::Prefix(b"user/".to_vec()));
+  let deletes = database.subscribe(ChangeFilter::Predicate(Box::new(|event| event.kind == ChangeKind::Delete)));
+
+  database.put(&write_opts, &"user/1", b"x").unwrap();
+  database.put(&write_opts, &"order/1", b"y").unwrap();
+  database.delete(&write_opts, &"order/1").unwrap();
+
+  let users: Vec<Vec<u8>> = users.try_iter().map(|event| event.key).collect();
+  assert_eq!(users, vec![b"user/1".to_vec()]);
+  let deletes: Vec<Vec<u8>> = deletes.try_iter().map(|event| event.key).collect();
+  assert_eq!(deletes, vec![b"order/1".to_vec()]);
+}
+
+#[test]
+fn test_predicate_writes_to_database() {
+  let tmp = temp_dir("changes");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let write_opts = WriteOptions::new();
+  let handle = Arc::downgrade(&database);
+  let events = database.subscribe(ChangeFilter::Predicate(Box::new(move |event| {
+    if let (Some(database), Some(user)) = (handle.upgrade(), event.key.strip_prefix(b"user/")) {
+      database.put_u8(&WriteOptions::new(), &[b"audit/", user].concat(), b"").unwrap();
+    }
+    true
+  })));
+
+  database.put(&write_opts, &"user/1", b"x").unwrap();
+  let keys: Vec<Vec<u8>> = events.try_iter().map(|event| event.key).collect();
+  assert_eq!(keys, vec![b"user/1".to_vec(), b"audit/1".to_vec()]);
+}
+
+#[test]
+fn test_dropped_receiver_unsubscribes() {
+  let tmp = temp_dir("changes");
+  let database = open_database(tmp.path(), true);
+  drop(database.subscribe(ChangeFilter::All));
+
+  database.put(&WriteOptions::new(), &"a", b"1").unwrap();
+  database.put(&WriteOptions::new(), &"a", b"2").unwrap();
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "cli")]
+
+mod utils;
+
+use utils::temp_dir;
+use std::path::Path;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn cli(db: &Path, args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_leveldb-cli"))
+    .arg(db)
+    .args(args)
+    .output()
+    .unwrap()
+}
+
+fn stdout(output: Output) -> String {
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_cli_put_get_scan() {
+  let tmp = temp_dir("cli");
+  let db = tmp.path().join("db");
+
+  stdout(cli(&db, &["--create", "put", "user/1", "ann"]));
+  stdout(cli(&db, &["put", "user/2", "bob"]));
+  stdout(cli(&db, &["--key-encoding", "hex", "put", "ff00", "raw"]));
+  stdout(cli(&db, &["put", "order/1", "x"]));
+
+  assert_eq!(stdout(cli(&db, &["get", "user/1"])), "ann\n");
+  assert_eq!(stdout(cli(&db, &["--value-encoding", "base64", "get", "user/2"])), "Ym9i\n");
+  assert_eq!(stdout(cli(&db, &["scan", "--prefix", "user/"])), "user/1\tann\nuser/2\tbob\n");
+  assert_eq!(stdout(cli(&db, &["scan", "--from", "order/", "--to", "user/2", "--keys-only"])), "order/1\nuser/1\n");
+  assert_eq!(stdout(cli(&db, &["scan", "--from", "user/2", "--limit", "1", "--keys-only"])), "user/2\n");
+  assert_eq!(stdout(cli(&db, &["scan", "--from", "user/2", "--keys-only"])), "user/2\n\\xff\\x00\n");
+  assert_eq!(stdout(cli(&db, &["count"])), "4\n");
+  // printed keys can be passed back
+  assert_eq!(stdout(cli(&db, &["get", "\\xff\\x00"])), "raw\n");
+  stdout(cli(&db, &["put", "back\\\\slash", "\\x01"]));
+  assert_eq!(stdout(cli(&db, &["scan", "--prefix", "back"])), "back\\\\slash\t\\x01\n");
+  assert_eq!(stdout(cli(&db, &["--key-encoding", "hex", "get", "6261636b5c736c617368"])), "\\x01\n");
+  assert!(!cli(&db, &["get", "\\q"]).status.success());
+  stdout(cli(&db, &["delete", "back\\\\slash"]));
+
+  stdout(cli(&db, &["delete", "user/1"]));
+  assert!(!cli(&db, &["get", "user/1"]).status.success());
+  stdout(cli(&db, &["compact"]));
+  stdout(cli(&db, &["stats"]));
+}
+
+#[test]
+fn test_cli_dump_load_destroy() {
+  let tmp = temp_dir("cli");
+  let db = tmp.path().join("db");
+  let copy = tmp.path().join("copy");
+  let dump = tmp.path().join("dump.jsonl");
+
+  stdout(cli(&db, &["--create", "put", "a", "1"]));
+  stdout(cli(&db, &["dump", "--format", "jsonl", "--output", dump.to_str().unwrap()]));
+  stdout(cli(&copy, &["--create", "load", "--input", dump.to_str().unwrap()]));
+  assert_eq!(stdout(cli(&copy, &["get", "a"])), "1\n");
+
+  assert!(!cli(&copy, &["destroy"]).status.success());
+  stdout(cli(&copy, &["destroy", "--yes"]));
+  assert!(!copy.exists() || !copy.join("CURRENT").exists());
+}
+
+#[test]
+fn test_cli_shell() {
+  let tmp = temp_dir("cli");
+  let db = tmp.path().join("db");
+  let history = tmp.path().join("history");
+  stdout(cli(&db, &["--create", "put", "a", "1"]));
+
+  let mut shell = Command::new(env!("CARGO_BIN_EXE_leveldb-cli"))
+    .arg(&db)
+    .args(["shell", "--page-size", "2", "--history", history.to_str().unwrap()])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  let input = "put b 2\nput \"c d\" 3\nsnapshot\nput e 4\nget e\nscan --keys-only\nnext\nrelease\nget e\ncount\nexit\n";
+  shell.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+  let output = stdout(shell.wait_with_output().unwrap());
+
+  let lines: Vec<_> = output.lines().map(|line| line.rsplit("> ").next().unwrap()).filter(|line| !line.is_empty()).collect();
+  assert_eq!(lines, ["snapshot pinned", "(not found)", "a", "b", "-- more, enter `next` to continue --", "c d",
+                     "snapshot released", "4", "4"]);
+  assert!(std::fs::read_to_string(&history).unwrap().contains("put e 4"));
+}
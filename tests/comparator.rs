@@ -2,7 +2,8 @@ use libc::c_char;
 mod utils;
 use utils::{temp_dir, db_put_u8_simple};
 use leveldb::database::Database;
-use leveldb::iterator::Iterable;
+use leveldb::iterator::{Iterable, LevelDBIterator};
+use leveldb::snapshots::Snapshots;
 use leveldb::options::{Options, ReadOptions};
 use leveldb::comparator::Comparator;
 use std::cmp::Ordering;
@@ -40,3 +41,44 @@ fn test_comparator() {
 }
 
 
+
+#[test]
+fn test_comparator_range() {
+  let comparator: ReverseComparator = ReverseComparator {};
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+
+  let tmp = temp_dir("reverse_comparator_range");
+  let database = &mut Database::open_with_comparator(tmp.path(), &opts, comparator).unwrap();
+  for i in 1..=5u8 {
+    db_put_u8_simple(database, &[i], &[i]);
+  }
+
+  let read_opts = ReadOptions::new();
+  let keys: Vec<Vec<u8>> = database.range(&read_opts, &[4u8][..]..&[1u8][..]).map(|(k, _)| k).collect();
+  assert_eq!(keys, vec![vec![4], vec![3], vec![2]]);
+}
+
+#[test]
+fn test_prefix_iter_with_comparator() {
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+  let tmp = temp_dir("reverse_comparator");
+  let database = Database::open_with_comparator(tmp.path(), &opts, ReverseComparator).unwrap();
+  for key in [&b"a"[..], b"ab", b"ac", b"b", b"c"] {
+    db_put_u8_simple(&database, key, b"v");
+  }
+
+  let read_opts = ReadOptions::new();
+  let keys = |prefix: &[u8]| database.prefix_keys_iter(&read_opts, prefix).collect::<Vec<_>>();
+  assert_eq!(keys(b"a"), vec![b"ac".to_vec(), b"ab".to_vec(), b"a".to_vec()]);
+  assert_eq!(keys(b"b"), vec![b"b".to_vec()]);
+  assert_eq!(keys(b"c"), vec![b"c".to_vec()]);
+  assert!(keys(b"d").is_empty());
+  assert!(keys(b"aa").is_empty());
+
+  let reversed: Vec<Vec<u8>> = database.prefix_keys_iter(&read_opts, b"a").reverse().collect();
+  assert_eq!(reversed, vec![b"a".to_vec(), b"ab".to_vec(), b"ac".to_vec()]);
+  let snapshot = database.snapshot();
+  assert_eq!(snapshot.prefix_keys_iter(&read_opts, b"a").count(), 3);
+}
@@ -0,0 +1,87 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::encoding::{encode, decode, Ordered, OrderedEncode};
+use leveldb::iterator::{Iterable, LevelDBIterator};
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::typed::{RawCodec, TypedDatabase};
+use leveldb::util::FromU8;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+fn assert_sorted<T: OrderedEncode + Debug>(values: &[T]) {
+  for pair in values.windows(2) {
+    assert!(encode(&pair[0]) < encode(&pair[1]), "{:?} should sort before {:?}", pair[0], pair[1]);
+  }
+}
+
+#[test]
+fn test_signed_integer_order() {
+  assert_sorted(&[i8::MIN, -1, 0, 1, i8::MAX]);
+  assert_sorted(&[i64::MIN, -1000, -1, 0, 1, 1000, i64::MAX]);
+  assert_eq!(decode::<i32>(&encode(&-42i32)).unwrap(), -42);
+  assert_eq!(decode::<i128>(&encode(&i128::MIN)).unwrap(), i128::MIN);
+}
+
+#[test]
+fn test_float_order() {
+  assert_sorted(&[f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1e-300, 2.5, f64::INFINITY, f64::NAN]);
+  assert_sorted(&[-1.0f32, 0.5, 3.0]);
+  assert_eq!(decode::<f64>(&encode(&-2.25f64)).unwrap(), -2.25);
+  assert!(decode::<f32>(&encode(&f32::NAN)).unwrap().is_nan());
+}
+
+#[test]
+fn test_string_order() {
+  assert_sorted(&["", "a", "a\0", "a\0b", "ab", "b"]);
+  assert_sorted(&[vec![0u8], vec![0, 0], vec![0, 1], vec![1]]);
+  assert_eq!(decode::<String>(&encode("nul\0inside")).unwrap(), "nul\0inside");
+  assert!(decode::<String>(&[b'a', 0]).is_err());
+  assert!(decode::<Vec<u8>>(&[0, 7]).is_err());
+}
+
+#[test]
+fn test_tuple_order() {
+  assert_sorted(&[("a", -1i32), ("a", 5), ("a\0", -10), ("b", i32::MIN)]);
+  assert_sorted(&[(1u8, -0.5f64, true), (1, 0.0, false), (2, -1.0, false)]);
+
+  let key = (String::from("user"), -7i64, vec![0u8, 255]);
+  assert_eq!(decode::<(String, i64, Vec<u8>)>(&encode(&key)).unwrap(), key);
+  assert!(decode::<(String, i64)>(&encode(&("user", 1i64, 2u8))).is_err());
+}
+
+#[test]
+fn test_ordered_key_range() {
+  let tmp = temp_dir("ordered_range");
+  let database = &mut open_database(tmp.path(), true);
+  for i in -3i32..3 {
+    db_put_simple(database, &Ordered(i), &[]);
+  }
+
+  let read_opts = ReadOptions::new();
+  let keys: Vec<i32> = database.range(&read_opts, Ordered(-2)..Ordered(2))
+    .map(|(k, _)| Ordered::<i32>::from_u8(&k).0)
+    .collect();
+  assert_eq!(keys, vec![-2, -1, 0, 1]);
+
+  let keys: Vec<i32> = database.keys_iter(&read_opts).reverse()
+    .map(|k| Ordered::<i32>::from_u8(&k).0)
+    .collect();
+  assert_eq!(keys, vec![2, 1, 0, -1, -2, -3]);
+}
+
+#[test]
+fn test_typed_composite_keys() {
+  let tmp = temp_dir("typed_composite");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<(String, i64), Vec<u8>, RawCodec> = TypedDatabase::new(database);
+  let write_opts = WriteOptions::new();
+  for (user, time) in [("bob", 5), ("alice", 10), ("bob", -5), ("alice", -10)] {
+    typed.put(&write_opts, &(user.to_string(), time), &vec![]).unwrap();
+  }
+
+  let read_opts = ReadOptions::new();
+  let from = ("bob".to_string(), i64::MIN);
+  let keys: Vec<(String, i64)> = typed.range(&read_opts, from..).map(|e| e.unwrap().0).collect();
+  assert_eq!(keys, vec![("bob".to_string(), -5), ("bob".to_string(), 5)]);
+}
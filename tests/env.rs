@@ -0,0 +1,59 @@
+mod utils;
+
+use utils::temp_dir;
+use utils::{open_in_memory_database, db_put_u8_simple};
+use leveldb::database::Database;
+use leveldb::env::Env;
+use leveldb::options::Options;
+use leveldb::options::ReadOptions;
+use leveldb::iterator::Iterable;
+
+#[test]
+fn test_open_with_default_env() {
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+  opts.env = Some(Env::new());
+
+  let tmp = temp_dir("default_env");
+  let res: Result<Database,_> = Database::open(tmp.path(), &opts);
+
+  assert!(res.is_ok());
+}
+
+#[test]
+fn test_open_in_memory() {
+  let database = open_in_memory_database();
+  db_put_u8_simple(&database, &[1], &[1]);
+  db_put_u8_simple(&database, &[2], &[2]);
+
+  let read_opts = ReadOptions::new();
+  assert_eq!(database.get_u8(&read_opts, &[1]).unwrap(), Some(vec![1]));
+  assert_eq!(database.iter(&read_opts).count(), 2);
+}
+
+#[test]
+fn test_in_memory_databases_are_independent() {
+  let first = open_in_memory_database();
+  let second = open_in_memory_database();
+  db_put_u8_simple(&first, &[1], &[1]);
+
+  let read_opts = ReadOptions::new();
+  assert_eq!(second.get_u8(&read_opts, &[1]).unwrap(), None);
+}
+
+#[test]
+fn test_open_with_in_memory_env() {
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+  opts.env = Some(Env::in_memory());
+
+  let database = Database::open(std::path::Path::new("/does/not/exist"), &opts).unwrap();
+  db_put_u8_simple(&database, &[1], &[1]);
+  drop(database);
+
+  // the environment still holds the files of the closed database
+  opts.create_if_missing = false;
+  let database = Database::open(std::path::Path::new("/does/not/exist"), &opts).unwrap();
+  let read_opts = ReadOptions::new();
+  assert_eq!(database.get_u8(&read_opts, &[1]).unwrap(), Some(vec![1]));
+}
@@ -0,0 +1,80 @@
+use libc::c_char;
+mod utils;
+use utils::{temp_dir, db_put_u8_simple};
+use leveldb::database::Database;
+use leveldb::compaction::Compaction;
+use leveldb::filter_policy::{Filter, FilterPolicy};
+use leveldb::options::{Options, ReadOptions};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Stores all keys verbatim, counting the lookups
+struct ExactFilter {
+    lookups: Arc<AtomicUsize>,
+}
+
+impl FilterPolicy for ExactFilter {
+    fn name(&self) -> *const c_char {
+        "exact\0".as_ptr() as *const c_char
+    }
+
+    fn create_filter(&self, keys: &[&[u8]]) -> Vec<u8> {
+        let mut filter = Vec::new();
+        for key in keys {
+            filter.push(key.len() as u8);
+            filter.extend_from_slice(key);
+        }
+        filter
+    }
+
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        let mut rest = filter;
+        while !rest.is_empty() {
+            let len = rest[0] as usize;
+            if &rest[1..1 + len] == key {
+                return true;
+            }
+            rest = &rest[1 + len..];
+        }
+        false
+    }
+}
+
+#[test]
+fn test_bloom_filter() {
+    let mut opts = Options::new();
+    opts.create_if_missing = true;
+    opts.filter_policy = Some(Filter::bloom(10));
+
+    let tmp = temp_dir("bloom_filter");
+    let database = Database::open(tmp.path(), &opts).unwrap();
+    db_put_u8_simple(&database, &[1], &[1]);
+    database.compact(&[0], &[2]);
+
+    let read_opts = ReadOptions::new();
+    assert_eq!(database.get_u8(&read_opts, &[1]).unwrap(), Some(vec![1]));
+    assert_eq!(database.get_u8(&read_opts, &[3]).unwrap(), None);
+}
+
+#[test]
+fn test_custom_filter_policy() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let mut opts = Options::new();
+    opts.create_if_missing = true;
+    opts.filter_policy = Some(Filter::new(ExactFilter { lookups: lookups.clone() }));
+
+    let tmp = temp_dir("custom_filter");
+    let database = Database::open(tmp.path(), &opts).unwrap();
+    // the database keeps the filter alive
+    drop(opts);
+
+    db_put_u8_simple(&database, &[1], &[1]);
+    db_put_u8_simple(&database, &[3], &[3]);
+    database.compact(&[0], &[4]);
+
+    let read_opts = ReadOptions::new();
+    assert_eq!(database.get_u8(&read_opts, &[3]).unwrap(), Some(vec![3]));
+    assert_eq!(database.get_u8(&read_opts, &[2]).unwrap(), None);
+    assert!(lookups.load(Ordering::SeqCst) >= 2);
+}
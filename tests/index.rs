@@ -0,0 +1,81 @@
+mod utils;
+
+use utils::{open_database, temp_dir};
+use leveldb::index::IndexedDatabase;
+use leveldb::options::{ReadOptions, WriteOptions};
+use std::sync::Arc;
+
+// indexes "name:city" values by city
+fn by_city(_key: &[u8], value: &[u8]) -> Vec<Vec<u8>> {
+  value.splitn(2, |b| *b == b':').nth(1).map(|city| vec![city.to_vec()]).unwrap_or_default()
+}
+
+fn lookup(db: &IndexedDatabase, city: &[u8]) -> Vec<Vec<u8>> {
+  db.lookup_by_index(&ReadOptions::new(), "city", city).unwrap()
+    .map(|entry| entry.unwrap().0)
+    .collect()
+}
+
+#[test]
+fn test_index_follows_writes() {
+  let tmp = temp_dir("index");
+  let mut db = IndexedDatabase::new(Arc::new(open_database(tmp.path(), true)));
+  db.add_index("city", by_city).unwrap();
+  let write_opts = WriteOptions::new();
+
+  db.put(&write_opts, &"u1", b"ann:paris").unwrap();
+  db.put(&write_opts, &"u2", b"bob:oslo").unwrap();
+  db.put(&write_opts, &"u3", b"cid:paris").unwrap();
+  assert_eq!(lookup(&db, b"paris"), vec![b"u1".to_vec(), b"u3".to_vec()]);
+
+  db.put(&write_opts, &"u1", b"ann:oslo").unwrap();
+  db.delete(&write_opts, &"u3").unwrap();
+  assert_eq!(lookup(&db, b"paris"), Vec::<Vec<u8>>::new());
+  assert_eq!(lookup(&db, b"oslo"), vec![b"u1".to_vec(), b"u2".to_vec()]);
+
+  let (key, value) = db.lookup_by_index(&ReadOptions::new(), "city", b"oslo").unwrap().next().unwrap().unwrap();
+  assert_eq!((key, value), (b"u1".to_vec(), b"ann:oslo".to_vec()));
+}
+
+#[test]
+fn test_rebuild_index() {
+  let tmp = temp_dir("index");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let write_opts = WriteOptions::new();
+  database.put(&write_opts, &"u1", b"ann:paris").unwrap();
+  database.put(&write_opts, &"u2", b"bob:oslo").unwrap();
+
+  let mut db = IndexedDatabase::new(database);
+  db.add_index("city", by_city).unwrap();
+  assert!(lookup(&db, b"paris").is_empty());
+
+  assert_eq!(db.rebuild_index(&write_opts, "city").unwrap(), 2);
+  assert_eq!(lookup(&db, b"paris"), vec![b"u1".to_vec()]);
+  assert_eq!(db.rebuild_index(&write_opts, "city").unwrap(), 2);
+  assert_eq!(lookup(&db, b"oslo"), vec![b"u2".to_vec()]);
+}
+
+#[test]
+fn test_lookup_skips_stale_entries() {
+  let tmp = temp_dir("index");
+  let mut db = IndexedDatabase::new(Arc::new(open_database(tmp.path(), true)));
+  db.add_index("city", by_city).unwrap();
+  let write_opts = WriteOptions::new();
+
+  db.put(&write_opts, &"u1", b"ann:paris").unwrap();
+  db.put(&write_opts, &"u2", b"bob:paris").unwrap();
+  db.database().put(&write_opts, &"u1", b"ann:oslo").unwrap();
+  db.database().delete(&write_opts, &"u2").unwrap();
+  assert!(lookup(&db, b"paris").is_empty());
+}
+
+#[test]
+fn test_unknown_and_duplicate_index() {
+  let tmp = temp_dir("index");
+  let mut db = IndexedDatabase::new(Arc::new(open_database(tmp.path(), true)));
+  db.add_index("city", by_city).unwrap();
+
+  assert!(db.add_index("city", by_city).is_err());
+  assert!(db.lookup_by_index(&ReadOptions::new(), "age", b"1").is_err());
+  assert_eq!(db.index_names(), vec!["city"]);
+}
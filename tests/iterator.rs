@@ -2,8 +2,11 @@ mod utils;
 use utils::{open_database,temp_dir, db_put_simple, db_put_u8_simple};
 use leveldb::iterator::Iterable;
 use leveldb::iterator::LevelDBIterator;
+use leveldb::iterator::{OwnedIterable, OwnedIterator};
 use leveldb::options::{ReadOptions};
 use leveldb::util::FromU8;
+use std::sync::Arc;
+use std::thread;
 
 #[test]
 fn test_iterator() {
@@ -68,6 +71,33 @@ fn test_iterator_last() {
     assert!(iter.last().is_some());
 }
 
+#[test]
+fn test_rev_iterator_reverse_restarts_at_first() {
+    let tmp = temp_dir("iter_reverse_twice");
+    let database = &mut open_database(tmp.path(), true);
+    for i in 1..4u8 {
+        db_put_u8_simple(database, &[i], &[i]);
+    }
+
+    let read_opts = ReadOptions::new();
+    let keys: Vec<Vec<u8>> = database.iter(&read_opts).reverse().reverse().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![1], vec![2], vec![3]]);
+
+    let keys: Vec<Vec<u8>> = database.keys_iter(&read_opts).reverse().reverse().collect();
+    assert_eq!(keys, vec![vec![1], vec![2], vec![3]]);
+}
+
+#[test]
+fn test_last_of_empty_iterators() {
+    let tmp = temp_dir("iter_last_empty");
+    let database = &mut open_database(tmp.path(), true);
+
+    let read_opts = ReadOptions::new();
+    assert!(database.iter(&read_opts).last().is_none());
+    assert!(database.keys_iter(&read_opts).last().is_none());
+    assert!(database.value_iter(&read_opts).last().is_none());
+}
+
 #[test]
 fn test_iterator_seek() {
     let tmp = temp_dir("from_to");
@@ -115,3 +145,238 @@ fn test_value_iterator() {
     let value = iter.next().unwrap();
     assert_eq!(value, vec![1]);
 }
+
+#[test]
+fn test_rev_value_iterator() {
+    let tmp = temp_dir("rev_value_iter");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_u8_simple(database, &[1], &[10]);
+    db_put_u8_simple(database, &[2], &[20]);
+
+    let read_opts = ReadOptions::new();
+    let values: Vec<Vec<u8>> = database.value_iter(&read_opts).reverse().collect();
+    assert_eq!(values, vec![vec![20], vec![10]]);
+}
+
+#[test]
+fn test_range_iterator() {
+    let tmp = temp_dir("range");
+    let database = &mut open_database(tmp.path(), true);
+    for i in 1..=5u8 {
+        db_put_u8_simple(database, &[i], &[i]);
+    }
+
+    let read_opts = ReadOptions::new();
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[2u8][..]..&[4u8][..]).map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![2], vec![3]]);
+
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[2u8][..]..=&[4u8][..]).map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![2], vec![3], vec![4]]);
+
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, ..&[3u8][..]).map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![1], vec![2]]);
+
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[4u8][..]..).map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![4], vec![5]]);
+}
+
+#[test]
+fn test_range_iterator_reverse() {
+    let tmp = temp_dir("range_reverse");
+    let database = &mut open_database(tmp.path(), true);
+    for i in 1..=5u8 {
+        db_put_u8_simple(database, &[i], &[i]);
+    }
+
+    let read_opts = ReadOptions::new();
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[2u8][..]..&[4u8][..]).reverse().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![3], vec![2]]);
+
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[2u8][..]..=&[4u8][..]).reverse().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![4], vec![3], vec![2]]);
+
+    // the upper bound lies between two keys
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[1u8, 0][..]..&[3u8, 0][..]).reverse().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![3], vec![2]]);
+
+    // the upper bound lies past the last key
+    let keys: Vec<Vec<u8>> = database.range(&read_opts, &[4u8][..]..&[9u8][..]).reverse().map(|(k, _)| k).collect();
+    assert_eq!(keys, vec![vec![5], vec![4]]);
+}
+
+#[test]
+fn test_range_iterator_integer_keys() {
+    let tmp = temp_dir("range_int");
+    let database = &mut open_database(tmp.path(), true);
+    for i in 0..10i32 {
+        db_put_simple(database, &i, &[i as u8]);
+    }
+
+    let read_opts = ReadOptions::new();
+    let values: Vec<Vec<u8>> = database.range(&read_opts, 3..6).map(|(_, v)| v).collect();
+    assert_eq!(values, vec![vec![3], vec![4], vec![5]]);
+
+    let empty = database.range(&read_opts, 20..30);
+    assert!(empty.last().is_none());
+}
+
+#[test]
+fn test_prefix_iterator() {
+    let tmp = temp_dir("prefix");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_u8_simple(database, b"a", &[0]);
+    db_put_u8_simple(database, b"user:1", &[1]);
+    db_put_u8_simple(database, b"user:2", &[2]);
+    db_put_u8_simple(database, b"user;", &[3]);
+    db_put_u8_simple(database, b"z", &[4]);
+
+    let read_opts = ReadOptions::new();
+    let keys: Vec<Vec<u8>> = database.prefix_keys_iter(&read_opts, b"user:").collect();
+    assert_eq!(keys, vec![b"user:1".to_vec(), b"user:2".to_vec()]);
+
+    let keys: Vec<Vec<u8>> = database.prefix_keys_iter(&read_opts, b"user:").reverse().collect();
+    assert_eq!(keys, vec![b"user:2".to_vec(), b"user:1".to_vec()]);
+
+    let values: Vec<Vec<u8>> = database.prefix_value_iter(&read_opts, b"user:").reverse().collect();
+    assert_eq!(values, vec![vec![2], vec![1]]);
+
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = database.prefix_iter(&read_opts, b"user").collect();
+    assert_eq!(entries.len(), 3);
+
+    assert_eq!(database.prefix_iter(&read_opts, b"none").count(), 0);
+    assert!(database.prefix_keys_iter(&read_opts, b"none").last().is_none());
+}
+
+#[test]
+fn test_prefix_iterator_trailing_ff() {
+    let tmp = temp_dir("prefix_ff");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_u8_simple(database, &[1, 0xff], &[1]);
+    db_put_u8_simple(database, &[1, 0xff, 0xff], &[2]);
+    db_put_u8_simple(database, &[2], &[3]);
+    db_put_u8_simple(database, &[0xff], &[4]);
+    db_put_u8_simple(database, &[0xff, 0xff, 1], &[5]);
+
+    let read_opts = ReadOptions::new();
+    let values: Vec<Vec<u8>> = database.prefix_value_iter(&read_opts, &[1, 0xff]).reverse().collect();
+    assert_eq!(values, vec![vec![2], vec![1]]);
+
+    let values: Vec<Vec<u8>> = database.prefix_value_iter(&read_opts, &[0xff, 0xff]).reverse().collect();
+    assert_eq!(values, vec![vec![5]]);
+}
+
+#[test]
+fn test_raw_cursor() {
+    let tmp = temp_dir("cursor");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_u8_simple(database, &[1], &[10]);
+    db_put_u8_simple(database, &[2], &[20]);
+    db_put_u8_simple(database, &[3], &[30]);
+
+    let read_opts = ReadOptions::new();
+    let mut cursor = database.cursor(&read_opts);
+    assert_eq!(cursor.entry_ref(), Some((&[1u8][..], &[10u8][..])));
+
+    cursor.advance();
+    assert_eq!(cursor.key_ref(), Some(&[2u8][..]));
+    assert_eq!(cursor.value_ref(), Some(&[20u8][..]));
+
+    cursor.seek_to_last();
+    assert_eq!(cursor.key_ref(), Some(&[3u8][..]));
+    cursor.retreat();
+    assert_eq!(cursor.key_ref(), Some(&[2u8][..]));
+
+    cursor.seek(&[4]);
+    assert!(!cursor.valid());
+    assert_eq!(cursor.key_ref(), None);
+
+    cursor.seek_to_first();
+    let mut total = 0;
+    cursor.for_each(|_, value| total += value[0] as u32);
+    assert_eq!(total, 60);
+}
+
+#[test]
+fn test_range_cursor() {
+    let tmp = temp_dir("range_cursor");
+    let database = &mut open_database(tmp.path(), true);
+    for i in 1..=5u8 {
+        db_put_u8_simple(database, &[i], &[i]);
+    }
+
+    let read_opts = ReadOptions::new();
+    let mut keys = Vec::new();
+    database.range(&read_opts, &[2u8][..]..&[4u8][..]).into_cursor().for_each(|key, _| keys.push(key.to_vec()));
+    assert_eq!(keys, vec![vec![2], vec![3]]);
+}
+
+#[test]
+fn test_iterator_status() {
+    let tmp = temp_dir("status");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_u8_simple(database, &[1], &[1]);
+
+    let read_opts = ReadOptions::new();
+    let mut iter = database.iter(&read_opts);
+    assert_eq!(iter.by_ref().count(), 1);
+    assert!(iter.status().is_ok());
+
+    let entries: Vec<_> = database.iter(&read_opts).fallible().collect();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].is_ok());
+}
+
+#[test]
+fn test_iterator_reports_corruption() {
+    use leveldb::compaction::Compaction;
+    use std::fs;
+
+    let tmp = temp_dir("corruption");
+    let database = open_database(tmp.path(), true);
+    for i in 0..100u8 {
+        db_put_u8_simple(&database, &[i], &[i; 1000]);
+    }
+    database.compact(&[0], &[100]);
+    drop(database);
+
+    for entry in fs::read_dir(tmp.path()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "ldb") {
+            let mut data = fs::read(&path).unwrap();
+            for byte in data[10..2000].iter_mut() {
+                *byte ^= 0xff;
+            }
+            fs::write(&path, data).unwrap();
+        }
+    }
+
+    let database = open_database(tmp.path(), false);
+    let mut read_opts = ReadOptions::new();
+    read_opts.verify_checksums = true;
+
+    let mut iter = database.iter(&read_opts);
+    assert!(iter.by_ref().count() < 100);
+    assert!(iter.status().is_err());
+
+    let last = database.iter(&read_opts).fallible().last().unwrap();
+    assert!(last.is_err());
+}
+
+#[test]
+fn test_owned_iterator() {
+    let tmp = temp_dir("owned_iter");
+    let database = Arc::new(open_database(tmp.path(), true));
+    db_put_simple(&database, &1, &[1]);
+    db_put_simple(&database, &2, &[2]);
+    db_put_simple(&database, &3, &[3]);
+
+    let read_opts = ReadOptions::new();
+    let iter: OwnedIterator = database.owned_range(&read_opts, 2..);
+    drop(database);
+
+    let handle = thread::spawn(move || {
+        iter.map(|(k, v)| (i32::from_u8(&k), v)).collect::<Vec<(i32, Vec<u8>)>>()
+    });
+
+    assert_eq!(handle.join().unwrap(), vec![(2, vec![2]), (3, vec![3])]);
+}
@@ -0,0 +1,97 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::batch::{Batch, WriteBatch};
+use leveldb::options::{ReadOptions, WriteOptions};
+
+#[test]
+fn test_keyspaces_are_isolated() {
+  let tmp = temp_dir("keyspace");
+  let database = open_database(tmp.path(), true);
+  let users = database.keyspace("users").unwrap();
+  let orders = database.keyspace("orders").unwrap();
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  users.put(&write_opts, &"1", b"alice").unwrap();
+  orders.put(&write_opts, &"1", b"book").unwrap();
+  db_put_simple(&database, &"1", b"plain");
+
+  assert_eq!(users.get(&read_opts, &"1").unwrap(), Some(b"alice".to_vec()));
+  assert_eq!(orders.get(&read_opts, &"1").unwrap(), Some(b"book".to_vec()));
+  assert_eq!(database.get(&read_opts, &"1").unwrap(), Some(b"plain".to_vec()));
+
+  users.delete(&write_opts, &"1").unwrap();
+  assert_eq!(users.get(&read_opts, &"1").unwrap(), None);
+  assert_eq!(orders.get(&read_opts, &"1").unwrap(), Some(b"book".to_vec()));
+}
+
+#[test]
+fn test_keyspace_iterators() {
+  let tmp = temp_dir("keyspace_iter");
+  let database = open_database(tmp.path(), true);
+  let first = database.keyspace("first").unwrap();
+  let second = database.keyspace("second").unwrap();
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+  for key in ["a", "b", "c"] {
+    first.put(&write_opts, &key, key.as_bytes()).unwrap();
+    second.put(&write_opts, &key, b"other").unwrap();
+  }
+
+  let entries: Vec<(Vec<u8>, Vec<u8>)> = first.iter(&read_opts).collect();
+  assert_eq!(entries, vec![(b"a".to_vec(), b"a".to_vec()), (b"b".to_vec(), b"b".to_vec()), (b"c".to_vec(), b"c".to_vec())]);
+
+  let keys: Vec<Vec<u8>> = first.keys_iter(&read_opts).reverse().collect();
+  assert_eq!(keys, vec![b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]);
+
+  let keys: Vec<Vec<u8>> = first.range(&read_opts, "b"..).map(|(k, _)| k).collect();
+  assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec()]);
+
+  let keys: Vec<Vec<u8>> = first.range(&read_opts, .."b").reverse().map(|(k, _)| k).collect();
+  assert_eq!(keys, vec![b"a".to_vec()]);
+
+  assert_eq!(first.prefix_iter(&read_opts, b"c").count(), 1);
+  assert_eq!(second.value_iter(&read_opts).filter(|v| v == b"other").count(), 3);
+}
+
+#[test]
+fn test_keyspace_write_batch() {
+  let tmp = temp_dir("keyspace_batch");
+  let database = open_database(tmp.path(), true);
+  let accounts = database.keyspace("accounts").unwrap();
+  let log = database.keyspace("log").unwrap();
+  let read_opts = ReadOptions::new();
+  accounts.put(&WriteOptions::new(), &"stale", b"1").unwrap();
+
+  let batch = WriteBatch::new();
+  batch.put_in(&accounts, &"bob", b"10");
+  batch.put_in(&log, &1u64, b"credit bob");
+  batch.delete_in(&accounts, &"stale");
+  database.write(&WriteOptions::new(), &batch).unwrap();
+
+  assert_eq!(accounts.get(&read_opts, &"bob").unwrap(), Some(b"10".to_vec()));
+  assert_eq!(accounts.get(&read_opts, &"stale").unwrap(), None);
+  assert_eq!(log.get(&read_opts, &1u64).unwrap(), Some(b"credit bob".to_vec()));
+}
+
+#[test]
+fn test_keyspace_registry_is_persisted() {
+  let tmp = temp_dir("keyspace_registry");
+  let (first_id, second_id) = {
+    let database = open_database(tmp.path(), true);
+    let first = database.keyspace("first").unwrap();
+    first.put(&WriteOptions::new(), &"key", b"value").unwrap();
+    let second = database.keyspace("second").unwrap();
+    assert_eq!(database.keyspace("first").unwrap().id(), first.id());
+    (first.id(), second.id())
+  };
+  assert_ne!(first_id, second_id);
+
+  let database = open_database(tmp.path(), false);
+  assert_eq!(database.keyspace_names().unwrap(), vec!["first".to_string(), "second".to_string()]);
+  let first = database.keyspace("first").unwrap();
+  assert_eq!(first.id(), first_id);
+  assert_eq!(first.get(&ReadOptions::new(), &"key").unwrap(), Some(b"value".to_vec()));
+  assert_eq!(database.keyspace("third").unwrap().id(), second_id.max(first_id) + 1);
+}
@@ -0,0 +1,39 @@
+mod utils;
+
+use utils::{temp_dir, db_put_u8_simple};
+use leveldb::compaction::Compaction;
+use leveldb::database::Database;
+use leveldb::logger::{LogLevel, Logger};
+use leveldb::options::Options;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_info_log_callback() {
+  let tmp = temp_dir("logger");
+  let messages = Arc::new(Mutex::new(Vec::new()));
+  let received = messages.clone();
+
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+  opts.info_log = Some(Logger::new(move |message| received.lock().unwrap().push(message.to_string())));
+  let database = Database::open(tmp.path(), &opts).unwrap();
+  drop(opts);
+  db_put_u8_simple(&database, &[1], &[1]);
+  database.compact(&[0], &[2]);
+  drop(database);
+
+  let messages = messages.lock().unwrap();
+  assert!(messages.iter().any(|message| message.starts_with("Level-0 table #")), "{:?}", messages);
+  assert!(messages.iter().all(|message| !message.ends_with('\n')));
+  assert!(!tmp.path().join("LOG").exists());
+}
+
+#[test]
+fn test_log_levels() {
+  assert_eq!(LogLevel::of("Compaction error: IO error: disk full"), LogLevel::Error);
+  assert_eq!(LogLevel::of("Log #5: ignoring conversion error: Corruption"), LogLevel::Warn);
+  assert_eq!(LogLevel::of("Too many L0 files; waiting..."), LogLevel::Warn);
+  assert_eq!(LogLevel::of("Recovering log #5"), LogLevel::Info);
+  assert_eq!(LogLevel::of("Compacting 4@0 + 1@1 files"), LogLevel::Info);
+  assert_eq!(LogLevel::of("Delete type=2 #7"), LogLevel::Debug);
+}
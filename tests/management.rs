@@ -1,7 +1,24 @@
 use leveldb::management::*;
 use leveldb::options::*;
+use leveldb::iterator::Iterable;
+use leveldb::comparator::Comparator;
+use leveldb::database::Database;
+use libc::c_char;
+use std::cmp::Ordering;
 mod utils;
-use utils::{open_database, temp_dir};
+use utils::{open_database, temp_dir, db_put_simple};
+
+struct ReverseComparator;
+
+impl Comparator for ReverseComparator {
+    fn name(&self) -> *const c_char {
+        "reverse\0".as_ptr() as *const c_char
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        b.cmp(a)
+    }
+}
 
 #[test]
 fn test_destroy_database() {
@@ -27,3 +44,123 @@ fn test_repair_database() {
     assert!(res.is_ok());
 }
 
+fn dump_and_load(format: DumpFormat) {
+    let tmp = temp_dir("dump");
+    let database = open_database(&tmp.path().join("source"), true);
+    db_put_simple(&database, &"a", b"1");
+    db_put_simple(&database, &"b,c", b"\x00\n\"");
+    db_put_simple(&database, &"empty", b"");
+
+    let mut dump_bytes = Vec::new();
+    assert_eq!(dump(&database, &mut dump_bytes, format).unwrap(), 3);
+
+    let target = tmp.path().join("target");
+    let mut load_opts = LoadOptions::new();
+    load_opts.batch_size = 2;
+    assert_eq!(load_with(&target, &dump_bytes[..], &load_opts).unwrap(), 3);
+
+    let loaded = open_database(&target, false);
+    let expected: Vec<_> = database.iter(&ReadOptions::new()).collect();
+    assert_eq!(loaded.iter(&ReadOptions::new()).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_dump_and_load_binary() {
+    dump_and_load(DumpFormat::Binary);
+}
+
+#[test]
+fn test_dump_and_load_csv() {
+    dump_and_load(DumpFormat::Csv);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_dump_and_load_json_lines() {
+    dump_and_load(DumpFormat::JsonLines);
+}
+
+#[cfg(not(feature = "json"))]
+#[test]
+fn test_load_json_lines_requires_feature() {
+    let tmp = temp_dir("dump");
+    let database = open_database(&tmp.path().join("source"), true);
+    db_put_simple(&database, &"a", b"1");
+
+    let mut dump_bytes = Vec::new();
+    assert_eq!(dump(&database, &mut dump_bytes, DumpFormat::JsonLines).unwrap(), 1);
+    assert!(load(&tmp.path().join("target"), &dump_bytes[..]).is_err());
+}
+
+#[test]
+fn test_load_rejects_corrupt_binary_dump() {
+    let tmp = temp_dir("dump");
+    let database = open_database(&tmp.path().join("source"), true);
+    db_put_simple(&database, &"key", b"value");
+
+    let mut dump_bytes = Vec::new();
+    dump(&database, &mut dump_bytes, DumpFormat::Binary).unwrap();
+    let last = dump_bytes.len() - 1;
+    dump_bytes[last] ^= 0xff;
+
+    assert!(load(&tmp.path().join("target"), &dump_bytes[..]).is_err());
+    assert!(load(&tmp.path().join("other"), &dump_bytes[..10]).is_err());
+}
+
+#[test]
+fn test_corrupt_dump_leaves_database_unchanged() {
+    let tmp = temp_dir("dump");
+    let database = open_database(&tmp.path().join("source"), true);
+    for i in 0..10 {
+        db_put_simple(&database, &i, b"new");
+    }
+    let target = tmp.path().join("target");
+    db_put_simple(&open_database(&target, true), &0, b"old");
+
+    let mut dump_bytes = Vec::new();
+    dump(&database, &mut dump_bytes, DumpFormat::Binary).unwrap();
+    let last = dump_bytes.len() - 1;
+    dump_bytes[last] ^= 0xff;
+    let mut load_opts = LoadOptions::new();
+    load_opts.batch_size = 2;
+    assert!(load_with(&target, &dump_bytes[..], &load_opts).is_err());
+
+    let loaded = open_database(&target, false);
+    assert_eq!(loaded.get(&ReadOptions::new(), &0).unwrap(), Some(b"old".to_vec()));
+    assert_eq!(loaded.iter(&ReadOptions::new()).count(), 1);
+    assert!(!tmp.path().join("target.loading").exists());
+}
+
+#[test]
+fn test_load_rejects_oversized_field_length() {
+    let tmp = temp_dir("dump");
+    let mut dump_bytes = b"LDBDUMP\0".to_vec();
+    dump_bytes.extend_from_slice(&1u32.to_be_bytes());
+    dump_bytes.push(1);
+    dump_bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+    dump_bytes.extend_from_slice(b"key");
+
+    assert!(load(&tmp.path().join("target"), &dump_bytes[..]).is_err());
+}
+
+#[test]
+fn test_load_with_comparator() {
+    let tmp = temp_dir("dump");
+    let database = open_database(&tmp.path().join("source"), true);
+    db_put_simple(&database, &"a", b"1");
+    db_put_simple(&database, &"b", b"2");
+    let mut dump_bytes = Vec::new();
+    dump(&database, &mut dump_bytes, DumpFormat::Binary).unwrap();
+
+    let target = tmp.path().join("target");
+    let mut opts = Options::new();
+    opts.create_if_missing = true;
+    drop(Database::open_with_comparator(&target, &opts, ReverseComparator).unwrap());
+    // the target cannot be opened with the default comparator
+    assert!(load(&target, &dump_bytes[..]).is_err());
+    assert_eq!(load_with_comparator(&target, &dump_bytes[..], &LoadOptions::new(), ReverseComparator).unwrap(), 2);
+
+    let loaded = Database::open_with_comparator(&target, &Options::new(), ReverseComparator).unwrap();
+    let keys: Vec<Vec<u8>> = loaded.keys_iter(&ReadOptions::new()).collect();
+    assert_eq!(keys, vec![b"b".to_vec(), b"a".to_vec()]);
+}
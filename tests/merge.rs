@@ -0,0 +1,173 @@
+mod utils;
+
+use utils::{open_database, temp_dir};
+use leveldb::database::Database;
+use leveldb::batch::{Batch, WriteBatch};
+use leveldb::iterator::Iterable;
+use leveldb::merge::{AddU64, Append, MergeOperator, MERGE_PREFIX};
+use leveldb::options::{Options, ReadOptions, WriteOptions};
+use leveldb::snapshots::{ArcSnapshot, Snapshots};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+fn open_merge_database(path: &Path, operator: Arc<dyn MergeOperator>) -> Database {
+  let mut opts = Options::new();
+  opts.create_if_missing = true;
+  opts.merge_operator = Some(operator);
+  Database::open(path, &opts).unwrap()
+}
+
+fn operand_count(database: &Database) -> usize {
+  database.prefix_keys_iter(&ReadOptions::new(), MERGE_PREFIX).count()
+}
+
+#[test]
+fn test_merge_folds_on_get() {
+  let tmp = temp_dir("merge");
+  let database = open_merge_database(tmp.path(), Arc::new(AddU64));
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  assert_eq!(database.get(&read_opts, &"hits").unwrap(), None);
+  database.merge(&write_opts, &"hits", &1u64.to_be_bytes()).unwrap();
+  database.merge(&write_opts, &"hits", &2u64.to_be_bytes()).unwrap();
+  assert_eq!(database.get(&read_opts, &"hits").unwrap(), Some(3u64.to_be_bytes().to_vec()));
+
+  let snapshot = database.snapshot();
+  database.merge(&write_opts, &"hits", &4u64.to_be_bytes()).unwrap();
+  assert_eq!(snapshot.get(&read_opts, &"hits").unwrap(), Some(3u64.to_be_bytes().to_vec()));
+  assert_eq!(database.get(&read_opts, &"hits").unwrap(), Some(7u64.to_be_bytes().to_vec()));
+}
+
+#[test]
+fn test_arc_snapshot_folds_operands() {
+  let tmp = temp_dir("merge_arc_snapshot");
+  let database = Arc::new(open_merge_database(tmp.path(), Arc::new(AddU64)));
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  database.merge(&write_opts, &"hits", &5u64.to_be_bytes()).unwrap();
+  let snapshot = ArcSnapshot::new(database.clone());
+  database.merge(&write_opts, &"hits", &1u64.to_be_bytes()).unwrap();
+  assert_eq!(snapshot.get(&read_opts, &"hits").unwrap(), Some(5u64.to_be_bytes().to_vec()));
+  assert_eq!(database.get(&read_opts, &"hits").unwrap(), Some(6u64.to_be_bytes().to_vec()));
+}
+
+#[test]
+fn test_put_and_delete_discard_operands() {
+  let tmp = temp_dir("merge");
+  let database = open_merge_database(tmp.path(), Arc::new(Append));
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  database.merge(&write_opts, &"list", b"a").unwrap();
+  database.put(&write_opts, &"list", b"x").unwrap();
+  database.merge(&write_opts, &"list", b"b").unwrap();
+  assert_eq!(database.get(&read_opts, &"list").unwrap(), Some(b"xb".to_vec()));
+
+  database.delete(&write_opts, &"list").unwrap();
+  assert_eq!(database.get(&read_opts, &"list").unwrap(), None);
+  assert_eq!(operand_count(&database), 0);
+}
+
+#[test]
+fn test_batch_writes_discard_operands() {
+  let tmp = temp_dir("merge");
+  let database = open_merge_database(tmp.path(), Arc::new(Append));
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  database.merge(&write_opts, &"list", b"a").unwrap();
+  database.merge(&write_opts, &"other", b"o").unwrap();
+  let batch = WriteBatch::new();
+  batch.put(&"list", b"x");
+  database.write(&write_opts, &batch).unwrap();
+  database.merge(&write_opts, &"list", b"b").unwrap();
+  assert_eq!(database.get(&read_opts, &"list").unwrap(), Some(b"xb".to_vec()));
+  assert_eq!(database.get(&read_opts, &"other").unwrap(), Some(b"o".to_vec()));
+
+  let mut txn = database.transaction();
+  txn.put(&"list", b"y");
+  txn.delete(&"other");
+  txn.commit(&write_opts).unwrap();
+  assert_eq!(database.get(&read_opts, &"list").unwrap(), Some(b"y".to_vec()));
+  assert_eq!(database.get(&read_opts, &"other").unwrap(), None);
+  assert_eq!(operand_count(&database), 0);
+}
+
+#[test]
+fn test_collapse() {
+  let tmp = temp_dir("merge");
+  let database = open_merge_database(tmp.path(), Arc::new(Append));
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  for key in ["a", "b", "c"] {
+    database.merge(&write_opts, &key, b"1").unwrap();
+    database.merge(&write_opts, &key, b"2").unwrap();
+  }
+
+  assert_eq!(database.collapse(&write_opts, "b".."c").unwrap(), 1);
+  assert_eq!(operand_count(&database), 4);
+  assert_eq!(database.get(&read_opts, &"b").unwrap(), Some(b"12".to_vec()));
+
+  assert_eq!(database.collapse::<&str, _>(&write_opts, ..).unwrap(), 2);
+  assert_eq!(operand_count(&database), 0);
+  assert_eq!(database.get(&read_opts, &"a").unwrap(), Some(b"12".to_vec()));
+  assert_eq!(database.get(&read_opts, &"c").unwrap(), Some(b"12".to_vec()));
+}
+
+#[test]
+fn test_collapse_keeps_concurrent_puts() {
+  let tmp = temp_dir("merge");
+  let database = Arc::new(open_merge_database(tmp.path(), Arc::new(Append)));
+  let done = Arc::new(AtomicBool::new(false));
+
+  let collapsers: Vec<_> = (0..4).map(|_| {
+    let (database, done) = (database.clone(), done.clone());
+    thread::spawn(move || {
+      let write_opts = WriteOptions::new();
+      while !done.load(Ordering::SeqCst) {
+        database.merge(&write_opts, &"key", b"+").unwrap();
+        database.collapse::<&str, _>(&write_opts, ..).unwrap();
+      }
+    })
+  }).collect();
+
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+  for i in 0..1000 {
+    let value = format!("{:04}", i);
+    database.put(&write_opts, &"key", value.as_bytes()).unwrap();
+    // later merges may only append to the value just put
+    let current = database.get(&read_opts, &"key").unwrap().unwrap();
+    assert!(current.starts_with(value.as_bytes()), "the put of {} was lost", value);
+  }
+  done.store(true, Ordering::SeqCst);
+  for collapser in collapsers {
+    collapser.join().unwrap();
+  }
+}
+
+#[test]
+fn test_merge_order_survives_reopen() {
+  let tmp = temp_dir("merge");
+  let write_opts = WriteOptions::new();
+  {
+    let database = open_merge_database(tmp.path(), Arc::new(Append));
+    database.merge(&write_opts, &"log", b"a").unwrap();
+  }
+  let database = open_merge_database(tmp.path(), Arc::new(Append));
+  database.merge(&write_opts, &"log", b"b").unwrap();
+  assert_eq!(database.get(&ReadOptions::new(), &"log").unwrap(), Some(b"ab".to_vec()));
+}
+
+#[test]
+fn test_merge_without_operator() {
+  let tmp = temp_dir("merge");
+  let database = open_database(tmp.path(), true);
+
+  assert!(database.merge(&WriteOptions::new(), &"hits", b"1").is_err());
+}
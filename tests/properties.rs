@@ -0,0 +1,70 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_u8_simple};
+use leveldb::compaction::Compaction;
+use leveldb::properties::Properties;
+
+#[test]
+fn test_property() {
+    let tmp = temp_dir("property");
+    let database = open_database(tmp.path(), true);
+
+    assert!(database.property("leveldb.stats").is_some());
+    assert!(database.property("leveldb.unknown").is_none());
+}
+
+#[test]
+fn test_typed_properties() {
+    let tmp = temp_dir("typed_property");
+    let database = open_database(tmp.path(), true);
+    db_put_u8_simple(&database, &[1], &[1]);
+    db_put_u8_simple(&database, &[2], &[2]);
+    database.compact(&[0], &[3]);
+
+    let stats = database.stats().unwrap();
+    let files: u64 = stats.levels.iter().map(|level| level.files).sum();
+    assert_eq!(files, 1);
+
+    let sstables = database.sstables().unwrap();
+    assert_eq!(sstables.len(), 1);
+    assert_eq!(database.num_files_at_level(sstables[0].level), Some(1));
+    assert!(sstables[0].smallest.starts_with("'\\x01' @ "));
+    assert!(sstables[0].largest.starts_with("'\\x02' @ "));
+
+    assert!(database.approximate_memory_usage().is_some());
+    assert_eq!(database.num_files_at_level(7), None);
+}
+
+#[test]
+fn test_sstables_with_separators_in_keys() {
+    let tmp = temp_dir("sstables_separators");
+    let database = open_database(tmp.path(), true);
+    db_put_u8_simple(&database, b"a' @ 1 : 1 .. 'b", &[1]);
+    db_put_u8_simple(&database, b"c .. d", &[2]);
+    database.compact(b"a", b"d");
+
+    let sstables = database.sstables().unwrap();
+    assert_eq!(sstables.len(), 1);
+    assert!(sstables[0].smallest.starts_with("'a' @ 1 : 1 .. 'b' @ "));
+    assert!(sstables[0].largest.starts_with("'c .. d' @ "));
+}
+
+#[test]
+fn test_approximate_sizes() {
+    let tmp = temp_dir("approximate_sizes");
+    let database = open_database(tmp.path(), true);
+    let value = vec![7u8; 100_000];
+    for i in 0..100u32 {
+        db_put_u8_simple(&database, &i.to_be_bytes(), &value);
+    }
+    database.compact(&0u32.to_be_bytes(), &100u32.to_be_bytes());
+
+    let sizes = database.approximate_sizes(&[(&0u32, &50u32), (&50u32, &100u32), (&200u32, &300u32)]);
+    assert_eq!(sizes.len(), 3);
+    assert!(sizes[0] > 0);
+    assert!(sizes[1] > 0);
+    assert_eq!(sizes[2], 0);
+
+    let sizes = database.approximate_sizes_u8(&[(&[0][..], &[1][..])]);
+    assert!(sizes[0] > 0);
+}
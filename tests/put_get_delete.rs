@@ -55,3 +55,15 @@ fn test_get_from_filled_database() {
         Err(_) => { panic!("failed reading data") }
     }
 }
+
+#[test]
+fn test_multi_get() {
+    let tmp = temp_dir("multi_get");
+    let database = &mut open_database(tmp.path(), true);
+    db_put_simple(database, &1, &[1]);
+    db_put_simple(database, &3, &[3]);
+
+    let results = database.multi_get(&ReadOptions::new(), &[1, 2, 3]);
+    let values: Vec<Option<Vec<u8>>> = results.into_iter().map(|res| res.unwrap()).collect();
+    assert_eq!(values, vec![Some(vec![1]), None, Some(vec![3])]);
+}
@@ -0,0 +1,57 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::batch::{Batch, WriteBatch};
+use leveldb::iterator::Iterable;
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::range_delete::DeleteRangeOptions;
+use leveldb::snapshots::Snapshots;
+use leveldb::util::FromU8;
+
+#[test]
+fn test_delete_range_in_batches() {
+  let tmp = temp_dir("delete_range");
+  let database = open_database(tmp.path(), true);
+  for i in 0..25 {
+    db_put_simple(&database, &i, b"v");
+  }
+
+  let mut range_opts = DeleteRangeOptions::new();
+  range_opts.batch_size = 10;
+  range_opts.compact = true;
+  let mut reports = vec![];
+  let deleted = database.delete_range_with(&WriteOptions::new(), 5..30, &range_opts, |n| reports.push(n)).unwrap();
+
+  assert_eq!(deleted, 20);
+  assert_eq!(reports, vec![10, 20]);
+  let keys: Vec<i32> = database.keys_iter(&ReadOptions::new()).map(|k| i32::from_u8(&k)).collect();
+  assert_eq!(keys, (0..5).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_delete_range_empty() {
+  let tmp = temp_dir("delete_range");
+  let database = open_database(tmp.path(), true);
+  db_put_simple(&database, &"b", b"v");
+
+  assert_eq!(database.delete_range(&WriteOptions::new(), "c"..).unwrap(), 0);
+  assert_eq!(database.delete_range(&WriteOptions::new(), .."b").unwrap(), 0);
+  assert_eq!(database.delete_range(&WriteOptions::new(), "a"..="b").unwrap(), 1);
+}
+
+#[test]
+fn test_write_batch_delete_range() {
+  let tmp = temp_dir("delete_range");
+  let database = open_database(tmp.path(), true);
+  db_put_simple(&database, &"a1", b"v");
+  db_put_simple(&database, &"a2", b"v");
+  db_put_simple(&database, &"b1", b"v");
+
+  let batch = WriteBatch::new();
+  batch.put(&"b2", b"v");
+  assert_eq!(batch.delete_range(&database.snapshot(), "a".."b").unwrap(), 2);
+  database.write(&WriteOptions::new(), &batch).unwrap();
+
+  let keys: Vec<Vec<u8>> = database.keys_iter(&ReadOptions::new()).collect();
+  assert_eq!(keys, vec![b"b1".to_vec(), b"b2".to_vec()]);
+}
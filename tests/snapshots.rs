@@ -1,10 +1,12 @@
 mod utils;
 
 use utils::{open_database, temp_dir,db_put_simple};
-use leveldb::snapshots::Snapshots;
+use leveldb::snapshots::{Snapshots, ArcSnapshot};
 use leveldb::options::{ReadOptions};
-use leveldb::iterator::{Iterable};
+use leveldb::iterator::{Iterable, OwnedIterable};
 use leveldb::util::FromU8;
+use std::sync::Arc;
+use std::thread;
 
 #[test]
 fn test_snapshots() {
@@ -23,6 +25,21 @@ fn test_snapshots() {
   assert_eq!(None, res.unwrap());
 }
 
+#[test]
+fn test_snapshot_multi_get() {
+  let tmp = temp_dir("snapshots");
+  let database = &mut open_database(tmp.path(), true);
+
+  db_put_simple(database, &1, &[1]);
+  let snapshot = database.snapshot();
+  db_put_simple(database, &1, &[10]);
+  db_put_simple(database, &2, &[2]);
+
+  let results = snapshot.multi_get(&ReadOptions::new(), &[1, 2]);
+  assert_eq!(results[0].as_ref().unwrap(), &Some(vec![1]));
+  assert_eq!(results[1].as_ref().unwrap(), &None);
+}
+
 #[test]
 fn test_snapshot_iterator() {
   let tmp = temp_dir("snap_iterator");
@@ -46,3 +63,52 @@ fn test_snapshot_iterator() {
   let next = iter.next();
   assert_eq!(None, next);
 }
+
+#[test]
+fn test_snapshot_range() {
+  let tmp = temp_dir("snap_range");
+  let database = &mut open_database(tmp.path(), true);
+  db_put_simple(database, &1, &[1]);
+  db_put_simple(database, &3, &[3]);
+  let snapshot = database.snapshot();
+
+  db_put_simple(database, &2, &[2]);
+  let read_opts = ReadOptions::new();
+
+  let keys: Vec<i32> = snapshot.range(&read_opts, 1..=3).map(|(k, _)| i32::from_u8(&k)).collect();
+  assert_eq!(keys, vec![1, 3]);
+}
+
+#[test]
+fn test_snapshot_prefix_iterator() {
+  let tmp = temp_dir("snap_prefix");
+  let database = &mut open_database(tmp.path(), true);
+  db_put_simple(database, &"user:1", &[1]);
+  let snapshot = database.snapshot();
+
+  db_put_simple(database, &"user:2", &[2]);
+  let read_opts = ReadOptions::new();
+
+  assert_eq!(snapshot.prefix_keys_iter(&read_opts, b"user:").count(), 1);
+  assert_eq!(database.prefix_keys_iter(&read_opts, b"user:").count(), 2);
+}
+
+#[test]
+fn test_arc_snapshot_across_threads() {
+  let tmp = temp_dir("arc_snap");
+  let database = Arc::new(open_database(tmp.path(), true));
+  db_put_simple(&database, &1, &[1]);
+  let snapshot = ArcSnapshot::new(database.clone());
+
+  db_put_simple(&database, &2, &[2]);
+  drop(database);
+
+  let handle = thread::spawn(move || {
+    let read_opts = ReadOptions::new();
+    assert_eq!(snapshot.get(&read_opts, &1).unwrap(), Some(vec![1]));
+    assert_eq!(snapshot.get(&read_opts, &2).unwrap(), None);
+    snapshot.owned_keys_iter(&read_opts).map(|k| i32::from_u8(&k)).collect::<Vec<i32>>()
+  });
+
+  assert_eq!(handle.join().unwrap(), vec![1]);
+}
@@ -0,0 +1,103 @@
+mod utils;
+
+use utils::{open_database, temp_dir, db_put_simple};
+use leveldb::error::ErrorKind;
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::util::FromU8;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_transaction_commit() {
+  let tmp = temp_dir("txn");
+  let database = open_database(tmp.path(), true);
+  db_put_simple(&database, &"a", b"1");
+  let read_opts = ReadOptions::new();
+
+  let mut txn = database.transaction();
+  assert_eq!(txn.get(&read_opts, &"a").unwrap(), Some(b"1".to_vec()));
+  txn.put(&"a", b"2");
+  txn.put(&"b", b"3");
+  txn.delete(&"a");
+  assert_eq!(txn.get(&read_opts, &"a").unwrap(), None);
+  assert_eq!(txn.get(&read_opts, &"b").unwrap(), Some(b"3".to_vec()));
+  assert_eq!(database.get(&read_opts, &"b").unwrap(), None);
+
+  txn.commit(&WriteOptions::new()).unwrap();
+  assert_eq!(database.get(&read_opts, &"a").unwrap(), None);
+  assert_eq!(database.get(&read_opts, &"b").unwrap(), Some(b"3".to_vec()));
+}
+
+#[test]
+fn test_transaction_reads_snapshot() {
+  let tmp = temp_dir("txn_snapshot");
+  let database = open_database(tmp.path(), true);
+  db_put_simple(&database, &"a", b"1");
+
+  let txn = database.transaction();
+  db_put_simple(&database, &"a", b"2");
+  assert_eq!(txn.get(&ReadOptions::new(), &"a").unwrap(), Some(b"1".to_vec()));
+  txn.rollback();
+}
+
+#[test]
+fn test_transaction_conflict() {
+  let tmp = temp_dir("txn_conflict");
+  let database = open_database(tmp.path(), true);
+  let read_opts = ReadOptions::new();
+  let write_opts = WriteOptions::new();
+
+  let mut first = database.transaction();
+  let mut second = database.transaction();
+  let mut unrelated = database.transaction();
+  first.get(&read_opts, &"counter").unwrap();
+  second.get(&read_opts, &"counter").unwrap();
+  unrelated.get(&read_opts, &"other").unwrap();
+  first.put(&"counter", b"1");
+  second.put(&"counter", b"2");
+  unrelated.put(&"other", b"3");
+
+  first.commit(&write_opts).unwrap();
+  let error = second.commit(&write_opts).unwrap_err();
+  assert_eq!(error.kind(), ErrorKind::Conflict);
+  unrelated.commit(&write_opts).unwrap();
+
+  assert_eq!(database.get(&read_opts, &"counter").unwrap(), Some(b"1".to_vec()));
+  assert_eq!(database.get(&read_opts, &"other").unwrap(), Some(b"3".to_vec()));
+
+  // a transaction started after the commit sees the new value
+  let mut third = database.transaction();
+  third.get(&read_opts, &"counter").unwrap();
+  third.put(&"counter", b"4");
+  third.commit(&write_opts).unwrap();
+}
+
+#[test]
+fn test_transaction_concurrent_increments() {
+  let tmp = temp_dir("txn_threads");
+  let database = Arc::new(open_database(tmp.path(), true));
+  db_put_simple(&database, &"counter", &0u64.to_be_bytes());
+
+  let handles: Vec<_> = (0..4).map(|_| {
+    let database = database.clone();
+    thread::spawn(move || {
+      for _ in 0..50 {
+        loop {
+          let mut txn = database.transaction();
+          let value = txn.get(&ReadOptions::new(), &"counter").unwrap().unwrap();
+          txn.put(&"counter", &(u64::from_u8(&value) + 1).to_be_bytes());
+          match txn.commit(&WriteOptions::new()) {
+            Ok(()) => break,
+            Err(e) => assert_eq!(e.kind(), ErrorKind::Conflict),
+          }
+        }
+      }
+    })
+  }).collect();
+  for handle in handles {
+    handle.join().unwrap();
+  }
+
+  let value = database.get(&ReadOptions::new(), &"counter").unwrap().unwrap();
+  assert_eq!(u64::from_u8(&value), 200);
+}
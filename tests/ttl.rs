@@ -0,0 +1,83 @@
+mod utils;
+
+use utils::{open_database, temp_dir};
+use leveldb::iterator::Iterable;
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::ttl::{TtlDatabase, SweeperOptions};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_ttl_expiry() {
+  let tmp = temp_dir("ttl");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let ttl = TtlDatabase::new(database.clone());
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  ttl.put(&write_opts, &"forever", b"1").unwrap();
+  ttl.put_with_ttl(&write_opts, &"short", b"2", Duration::from_millis(50)).unwrap();
+  ttl.put_with_ttl(&write_opts, &"long", b"3", Duration::from_secs(3600)).unwrap();
+
+  assert_eq!(ttl.get(&read_opts, &"short").unwrap(), Some(b"2".to_vec()));
+  assert_eq!(ttl.ttl(&read_opts, &"forever").unwrap(), None);
+  assert!(ttl.ttl(&read_opts, &"long").unwrap().unwrap() > Duration::from_secs(3500));
+
+  thread::sleep(Duration::from_millis(100));
+
+  assert_eq!(ttl.get(&read_opts, &"short").unwrap(), None);
+  assert_eq!(ttl.get(&read_opts, &"forever").unwrap(), Some(b"1".to_vec()));
+  let keys: Vec<Vec<u8>> = ttl.keys_iter(&read_opts).collect();
+  assert_eq!(keys, vec![b"forever".to_vec(), b"long".to_vec()]);
+  let entries: Vec<(Vec<u8>, Vec<u8>)> = ttl.iter(&read_opts).reverse().collect();
+  assert_eq!(entries, vec![(b"long".to_vec(), b"3".to_vec()), (b"forever".to_vec(), b"1".to_vec())]);
+
+  // the expired entry is hidden, but still stored until purged
+  assert!(database.get(&read_opts, &"short").unwrap().is_some());
+  assert_eq!(ttl.purge_expired(10).unwrap(), 1);
+  assert!(database.get(&read_opts, &"short").unwrap().is_none());
+}
+
+#[test]
+fn test_ttl_rewrite_clears_expiry() {
+  let tmp = temp_dir("ttl_rewrite");
+  let ttl = TtlDatabase::new(Arc::new(open_database(tmp.path(), true)));
+  let write_opts = WriteOptions::new();
+
+  ttl.put_with_ttl(&write_opts, &"key", b"old", Duration::from_millis(20)).unwrap();
+  ttl.put(&write_opts, &"key", b"new").unwrap();
+  thread::sleep(Duration::from_millis(40));
+
+  assert_eq!(ttl.purge_expired(10).unwrap(), 0);
+  assert_eq!(ttl.get(&ReadOptions::new(), &"key").unwrap(), Some(b"new".to_vec()));
+}
+
+#[test]
+fn test_ttl_sweeper() {
+  let tmp = temp_dir("ttl_sweeper");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let ttl = TtlDatabase::new(database.clone());
+  let write_opts = WriteOptions::new();
+  for i in 0..25u32 {
+    ttl.put_with_ttl(&write_opts, &i, b"session", Duration::from_millis(10)).unwrap();
+  }
+  ttl.put(&write_opts, &100u32, b"kept").unwrap();
+  thread::sleep(Duration::from_millis(20));
+
+  let mut options = SweeperOptions::new();
+  options.interval = Duration::from_millis(10);
+  options.batch_size = 10;
+  options.pause = Duration::from_millis(1);
+  let sweeper = ttl.start_sweeper(options);
+
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while sweeper.removed() < 25 && Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(5));
+  }
+  sweeper.stop();
+
+  let read_opts = ReadOptions::new();
+  assert_eq!(database.keys_iter(&read_opts).count(), 1);
+  assert_eq!(ttl.get(&read_opts, &100u32).unwrap(), Some(b"kept".to_vec()));
+}
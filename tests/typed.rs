@@ -0,0 +1,111 @@
+mod utils;
+
+use utils::{open_database, temp_dir};
+use leveldb::error::Error;
+use leveldb::options::{ReadOptions, WriteOptions};
+use leveldb::typed::{Codec, TypedDatabase, TypedWriteBatch};
+use std::sync::Arc;
+
+struct Utf8Codec;
+
+impl Codec<String> for Utf8Codec {
+  fn encode(value: &String) -> Result<Vec<u8>, Error> {
+    Ok(value.as_bytes().to_vec())
+  }
+
+  fn decode(data: &[u8]) -> Result<String, Error> {
+    String::from_utf8(data.to_vec()).map_err(|e| Error::new(e.to_string()))
+  }
+}
+
+#[test]
+fn test_typed_put_get_delete() {
+  let tmp = temp_dir("typed");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<u32, String, Utf8Codec> = TypedDatabase::new(database);
+  let write_opts = WriteOptions::new();
+  let read_opts = ReadOptions::new();
+
+  typed.put(&write_opts, &1, &"one".to_string()).unwrap();
+  assert_eq!(typed.get(&read_opts, &1).unwrap(), Some("one".to_string()));
+
+  typed.delete(&write_opts, &1).unwrap();
+  assert_eq!(typed.get(&read_opts, &1).unwrap(), None);
+}
+
+#[test]
+fn test_typed_iterators() {
+  let tmp = temp_dir("typed_iter");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<u64, String, Utf8Codec> = TypedDatabase::new(database);
+  let read_opts = ReadOptions::new();
+
+  let batch = TypedWriteBatch::new();
+  for key in [300u64, 2, 1000, 1] {
+    batch.put(&key, &key.to_string()).unwrap();
+  }
+  batch.delete(&1000);
+  typed.write(&WriteOptions::new(), &batch).unwrap();
+
+  let entries: Vec<(u64, String)> = typed.iter(&read_opts).map(|e| e.unwrap()).collect();
+  assert_eq!(entries, vec![(1, "1".to_string()), (2, "2".to_string()), (300, "300".to_string())]);
+
+  let keys: Vec<u64> = typed.range(&read_opts, 2..=300).map(|e| e.unwrap().0).collect();
+  assert_eq!(keys, vec![2, 300]);
+
+  let keys: Vec<u64> = typed.keys_iter(&read_opts).map(|k| k.unwrap()).collect();
+  assert_eq!(keys, vec![1, 2, 300]);
+}
+
+#[test]
+fn test_typed_decode_error() {
+  let tmp = temp_dir("typed_err");
+  let database = Arc::new(open_database(tmp.path(), true));
+  database.put_u8(&WriteOptions::new(), &[0, 0, 0, 1], &[0xff]).unwrap();
+  let typed: TypedDatabase<u32, String, Utf8Codec> = TypedDatabase::new(database);
+  let read_opts = ReadOptions::new();
+
+  assert!(typed.get(&read_opts, &1).is_err());
+  assert!(typed.iter(&read_opts).next().unwrap().is_err());
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_typed_bincode() {
+  use leveldb::typed::Bincode;
+
+  let tmp = temp_dir("typed_bincode");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<String, (u32, Vec<String>), Bincode> = TypedDatabase::new(database);
+  let value = (7, vec!["a".to_string(), "b".to_string()]);
+
+  typed.put(&WriteOptions::new(), &"key".to_string(), &value).unwrap();
+  assert_eq!(typed.get(&ReadOptions::new(), &"key".to_string()).unwrap(), Some(value));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_typed_json() {
+  use leveldb::typed::Json;
+
+  let tmp = temp_dir("typed_json");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<u8, Vec<Option<i64>>, Json> = TypedDatabase::new(database.clone());
+
+  typed.put(&WriteOptions::new(), &1, &vec![Some(-1), None]).unwrap();
+  assert_eq!(database.get_u8(&ReadOptions::new(), &[1]).unwrap(), Some(b"[-1,null]".to_vec()));
+  assert_eq!(typed.get(&ReadOptions::new(), &1).unwrap(), Some(vec![Some(-1), None]));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_typed_cbor() {
+  use leveldb::typed::Cbor;
+
+  let tmp = temp_dir("typed_cbor");
+  let database = Arc::new(open_database(tmp.path(), true));
+  let typed: TypedDatabase<Vec<u8>, (bool, f64), Cbor> = TypedDatabase::new(database);
+
+  typed.put(&WriteOptions::new(), &vec![1, 2], &(true, 1.5)).unwrap();
+  assert_eq!(typed.get(&ReadOptions::new(), &vec![1, 2]).unwrap(), Some((true, 1.5)));
+}
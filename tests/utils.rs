@@ -20,6 +20,13 @@ pub fn open_database(path: &Path, create_if_missing: bool) -> Database {
   }
 }
 
+pub fn open_in_memory_database() -> Database {
+  match Database::open_in_memory(&Options::new()) {
+    Ok(db) => { db },
+    Err(e) => { panic!("failed to open database: {:?}", e) }
+  }
+}
+
 pub fn db_put_u8_simple(database: &Database, key: &[u8], val: &[u8]) {
   let write_opts = WriteOptions::new();
 